@@ -0,0 +1,61 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Every png in src/textures/sprites becomes a named sprite of the atlas.
+// Sprite name is the file name without extension.
+// Sprite sheets are named `<name>.<columns>x<rows>.png`, frames go row by row.
+// Frame rate of a sheet goes after the grid, e.g. `<name>.4x1.12fps.png`, 0 means default.
+fn main() {
+    let sprites_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("src/textures/sprites");
+
+    println!("cargo:rerun-if-changed={}", sprites_dir.display());
+
+    let mut entries: Vec<_> = fs::read_dir(&sprites_dir)
+        .expect("Failed to read sprites directory")
+        .map(|entry| entry.expect("Failed to read directory entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+
+    // keep atlas layout stable between builds
+    entries.sort();
+
+    let mut source = String::from("pub const SPRITES: &[(&str, &[u8], u32, u32, u32)] = &[\n");
+
+    for path in entries {
+        let stem = path.file_stem().unwrap().to_str().expect("Sprite name must be valid utf-8");
+
+        let (stem, fps) = parse_fps(stem);
+        let (name, columns, rows) = parse_sheet_name(stem);
+
+        source.push_str(&format!("    ({:?}, include_bytes!({:?}), {}, {}, {}),\n", name, path.display().to_string(), columns, rows, fps));
+    }
+
+    source.push_str("];\n");
+
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("sprites.rs");
+
+    fs::write(out_path, source).expect("Failed to write sprites list");
+}
+
+fn parse_fps(stem: &str) -> (&str, u32) {
+    if let Some((rest, rate)) = stem.rsplit_once('.') {
+        if let Some(Ok(fps)) = rate.strip_suffix("fps").map(str::parse) {
+            return (rest, fps);
+        }
+    }
+
+    (stem, 0)
+}
+
+fn parse_sheet_name(stem: &str) -> (&str, u32, u32) {
+    if let Some((name, grid)) = stem.rsplit_once('.') {
+        if let Some((columns, rows)) = grid.split_once('x') {
+            if let (Ok(columns), Ok(rows)) = (columns.parse(), rows.parse()) {
+                return (name, columns, rows);
+            }
+        }
+    }
+
+    (stem, 1, 1)
+}
@@ -0,0 +1,326 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::events::*;
+use crate::game_mode::ModeKind;
+use crate::special::Special;
+
+// Achievements are stored among unlocks with this prefix
+const UNLOCK_PREFIX: &str = "achievement.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    // hits in a row
+    Combo(u32),
+    // score in one game, of the given mode or of any
+    Score(Option<ModeKind>, u64),
+    // perfect hits in one game
+    PerfectHits(u32),
+    // hits of the special figure in one game
+    Special(Special, u32),
+    // game over with at least this many hits and no decoy or bomb hit
+    CleanGame(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Achievement {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub goal: Goal
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement { id: "combo_10", title: "On fire", description: "10 hits in a row", goal: Goal::Combo(10) },
+    Achievement { id: "combo_25", title: "Unstoppable", description: "25 hits in a row", goal: Goal::Combo(25) },
+    Achievement { id: "score_500", title: "Warming up", description: "Score 500 in one game", goal: Goal::Score(None, 500) },
+    Achievement { id: "time_attack_1000", title: "Against the clock", description: "Score 1000 in time attack", goal: Goal::Score(Some(ModeKind::TimeAttack), 1000) },
+    Achievement { id: "endless_1000", title: "Marathon", description: "Score 1000 in endless", goal: Goal::Score(Some(ModeKind::Endless), 1000) },
+    Achievement { id: "perfect_5", title: "Sharp eye", description: "5 perfect hits in one game", goal: Goal::PerfectHits(5) },
+    Achievement { id: "golden", title: "Gold digger", description: "Hit a golden figure", goal: Goal::Special(Special::Golden, 1) },
+    Achievement { id: "clean_game", title: "Gentle", description: "Finish a game with 10 hits and no decoy hit", goal: Goal::CleanGame(10) },
+];
+
+impl Achievement {
+    pub fn unlock_name(&self) -> String {
+        format!("{}{}", UNLOCK_PREFIX, self.id)
+    }
+}
+
+// Counters of the current game
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Progress {
+    mode: Option<ModeKind>,
+    hits: u32,
+    combo: u32,
+    perfect: u32,
+    score: u64,
+    // hits of every special
+    specials: Vec<(Special, u32)>,
+    // decoy or bomb was hit
+    mistake: bool,
+    over: bool
+}
+
+impl Progress {
+    fn special_hits(&self, special: Special) -> u32 {
+        self.specials.iter().find(|&&(s, _)| s == special).map_or(0, |&(_, hits)| hits)
+    }
+
+    fn reached(&self, goal: Goal) -> bool {
+        match goal {
+            Goal::Combo(combo) => self.combo >= combo,
+            Goal::Score(mode, score) => mode.is_none_or(|mode| self.mode == Some(mode)) && self.score >= score,
+            Goal::PerfectHits(perfect) => self.perfect >= perfect,
+            Goal::Special(special, hits) => self.special_hits(special) >= hits,
+            Goal::CleanGame(hits) => self.over && !self.mistake && self.hits >= hits
+        }
+    }
+}
+
+// Tracks game events and tells which achievements they unlock
+// knows nothing about the browser, so event streams can be replayed anywhere
+#[derive(Debug)]
+pub struct Achievements {
+    // ids of unlocked ones
+    unlocked: Vec<&'static str>,
+    progress: Progress
+}
+
+impl Achievements {
+    // `unlocks` are unlock names from the save
+    pub fn new(unlocks: &[String]) -> Achievements {
+        Achievements {
+            unlocked: ACHIEVEMENTS.iter()
+                .filter(|achievement| unlocks.contains(&achievement.unlock_name()))
+                .map(|achievement| achievement.id)
+                .collect(),
+            progress: Progress::default()
+        }
+    }
+
+    // Newly unlocked achievements
+    pub fn handle(&mut self, event: &GameEvent) -> Vec<&'static Achievement> {
+        let progress = &mut self.progress;
+
+        match event {
+            GameEvent::GameStarted { mode } => {
+                *progress = Progress { mode: Some(*mode), ..Progress::default() };
+            },
+            // counters of the game before the reload are lost
+            GameEvent::GameResumed { mode, score } => {
+                *progress = Progress { mode: Some(*mode), score: *score, ..Progress::default() };
+            },
+            GameEvent::TargetHit { hit, special, score } => {
+                progress.hits += 1;
+                progress.combo = hit.combo;
+                progress.score = *score;
+
+                if hit.is_perfect() {
+                    progress.perfect += 1;
+                }
+
+                if let Some(special) = special {
+                    match progress.specials.iter_mut().find(|(s, _)| s == special) {
+                        Some((_, hits)) => *hits += 1,
+                        None => progress.specials.push((*special, 1))
+                    }
+                }
+            },
+            GameEvent::DecoyHit | GameEvent::BombHit => {
+                progress.combo = 0;
+                progress.mistake = true;
+            },
+            GameEvent::TargetMissed => {
+                progress.combo = 0;
+            },
+            GameEvent::GameOver { mode, score } => {
+                progress.mode = Some(*mode);
+                progress.score = *score;
+                progress.over = true;
+            },
+            GameEvent::TargetSpawned { .. } | GameEvent::NewBest { .. } | GameEvent::AchievementUnlocked { .. } => {
+                return Vec::new();
+            }
+        }
+
+        let unlocked: Vec<&'static Achievement> = ACHIEVEMENTS.iter()
+            .filter(|achievement| !self.unlocked.contains(&achievement.id) && self.progress.reached(achievement.goal))
+            .collect();
+
+        self.unlocked.extend(unlocked.iter().map(|achievement| achievement.id));
+
+        unlocked
+    }
+
+    pub fn unlocked_count(&self) -> usize {
+        self.unlocked.len()
+    }
+}
+
+impl Subscriber for Achievements {
+    fn on_event(&mut self, event: &GameEvent, follow_ups: &mut Vec<GameEvent>) {
+        follow_ups.extend(self.handle(event).into_iter().map(|achievement| GameEvent::AchievementUnlocked { achievement }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::HitScore;
+
+    const ROUND_TIME: f64 = 2.0;
+
+    fn start(mode: ModeKind) -> GameEvent {
+        GameEvent::GameStarted { mode }
+    }
+
+    fn hit(combo: u32, score: u64) -> GameEvent {
+        GameEvent::TargetHit { hit: HitScore::new(combo, ROUND_TIME, ROUND_TIME, 0), special: None, score }
+    }
+
+    fn perfect_hit(combo: u32, score: u64) -> GameEvent {
+        GameEvent::TargetHit { hit: HitScore::new(combo, 0.0, ROUND_TIME, 0), special: None, score }
+    }
+
+    fn special_hit(special: Special, score: u64) -> GameEvent {
+        GameEvent::TargetHit { hit: HitScore::new(1, ROUND_TIME, ROUND_TIME, 0), special: Some(special), score }
+    }
+
+    // ids of achievements unlocked by the events
+    fn play(achievements: &mut Achievements, events: &[GameEvent]) -> Vec<&'static str> {
+        events.iter().flat_map(|event| achievements.handle(event)).map(|achievement| achievement.id).collect()
+    }
+
+    // hits with growing combo and 10 points each
+    fn hits(from: u32, to: u32) -> Vec<GameEvent> {
+        (from..=to).map(|combo| hit(combo, 10*combo as u64)).collect()
+    }
+
+    #[test]
+    fn combo_unlocks_at_goal() {
+        let mut achievements = Achievements::new(&[]);
+
+        assert_eq!(play(&mut achievements, &[start(ModeKind::Zen)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &hits(1, 9)), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[hit(10, 100)]), vec!["combo_10"]);
+        assert_eq!(play(&mut achievements, &hits(11, 25)), vec!["combo_25"]);
+    }
+
+    #[test]
+    fn combo_resets_on_decoy_hit_and_miss() {
+        for mistake in [GameEvent::DecoyHit, GameEvent::BombHit, GameEvent::TargetMissed] {
+            let mut achievements = Achievements::new(&[]);
+
+            play(&mut achievements, &[start(ModeKind::Zen), hit(9, 90)]);
+            assert_eq!(achievements.progress.combo, 9);
+
+            assert!(achievements.handle(&mistake).is_empty());
+            assert_eq!(achievements.progress.combo, 0, "{mistake:?}");
+
+            // the game counts the combo from the start again
+            assert_eq!(play(&mut achievements, &hits(1, 9)), Vec::<&str>::new());
+        }
+    }
+
+    #[test]
+    fn score_of_any_mode() {
+        let mut achievements = Achievements::new(&[]);
+
+        play(&mut achievements, &[start(ModeKind::Classic)]);
+
+        assert_eq!(play(&mut achievements, &[hit(1, 499)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[hit(1, 500)]), vec!["score_500"]);
+        // other modes do not count for mode goals
+        assert_eq!(play(&mut achievements, &[hit(1, 1500)]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn score_of_the_mode() {
+        let mut achievements = Achievements::new(&[]);
+
+        assert_eq!(play(&mut achievements, &[start(ModeKind::TimeAttack), hit(1, 1000)]), vec!["score_500", "time_attack_1000"]);
+        assert_eq!(play(&mut achievements, &[start(ModeKind::Endless), hit(1, 999)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[GameEvent::GameOver { mode: ModeKind::Endless, score: 1000 }]), vec!["endless_1000"]);
+    }
+
+    #[test]
+    fn resumed_game_keeps_its_score() {
+        let mut achievements = Achievements::new(&[]);
+
+        assert_eq!(play(&mut achievements, &[GameEvent::GameResumed { mode: ModeKind::Classic, score: 600 }]), vec!["score_500"]);
+    }
+
+    #[test]
+    fn perfect_and_special_hits() {
+        let mut achievements = Achievements::new(&[]);
+
+        play(&mut achievements, &[start(ModeKind::Zen)]);
+
+        assert_eq!(play(&mut achievements, &[special_hit(Special::Clock, 10)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[special_hit(Special::Golden, 20)]), vec!["golden"]);
+
+        let perfect: Vec<GameEvent> = (1..=5).map(|combo| perfect_hit(combo, 20 + combo as u64)).collect();
+        assert_eq!(play(&mut achievements, &perfect), vec!["perfect_5"]);
+    }
+
+    #[test]
+    fn clean_game_needs_game_over() {
+        let mut achievements = Achievements::new(&[]);
+        let over = GameEvent::GameOver { mode: ModeKind::Zen, score: 100 };
+
+        play(&mut achievements, &[start(ModeKind::Zen)]);
+
+        assert_eq!(play(&mut achievements, &hits(1, 9)), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[GameEvent::TargetMissed, hit(1, 100)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[over]), vec!["clean_game"]);
+    }
+
+    #[test]
+    fn clean_game_needs_enough_hits() {
+        let mut achievements = Achievements::new(&[]);
+
+        play(&mut achievements, &[start(ModeKind::Zen)]);
+        play(&mut achievements, &hits(1, 9));
+
+        assert_eq!(play(&mut achievements, &[GameEvent::GameOver { mode: ModeKind::Zen, score: 90 }]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn clean_game_fails_on_mistakes() {
+        for mistake in [GameEvent::DecoyHit, GameEvent::BombHit] {
+            let mut achievements = Achievements::new(&[]);
+
+            play(&mut achievements, &[start(ModeKind::Zen), mistake]);
+            play(&mut achievements, &hits(1, 10));
+
+            assert_eq!(play(&mut achievements, &[GameEvent::GameOver { mode: ModeKind::Zen, score: 100 }]), Vec::<&str>::new());
+
+            // the next game starts clean
+            play(&mut achievements, &[start(ModeKind::Zen)]);
+            play(&mut achievements, &hits(1, 10));
+
+            assert_eq!(play(&mut achievements, &[GameEvent::GameOver { mode: ModeKind::Zen, score: 100 }]), vec!["clean_game"]);
+        }
+    }
+
+    #[test]
+    fn unlocked_only_once() {
+        let mut achievements = Achievements::new(&[]);
+
+        assert_eq!(play(&mut achievements, &[start(ModeKind::Zen), hit(10, 100)]), vec!["combo_10"]);
+        assert_eq!(play(&mut achievements, &[hit(11, 110)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[start(ModeKind::Zen), hit(10, 100)]), Vec::<&str>::new());
+
+        assert_eq!(achievements.unlocked_count(), 1);
+    }
+
+    #[test]
+    fn saved_unlocks_are_kept() {
+        let unlocks = vec![ACHIEVEMENTS[6].unlock_name(), String::from("achievement.unknown"), String::from("skin.red")];
+        let mut achievements = Achievements::new(&unlocks);
+
+        assert_eq!(achievements.unlocked_count(), 1);
+        assert_eq!(play(&mut achievements, &[start(ModeKind::Zen), special_hit(Special::Golden, 10)]), Vec::<&str>::new());
+        assert_eq!(play(&mut achievements, &[hit(10, 100)]), vec!["combo_10"]);
+    }
+}
@@ -0,0 +1,235 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::atlas::*;
+
+// Frame duration of sheets which do not set their frame rate
+const DEFAULT_FRAME_TIME: f64 = 0.1; // 10 fps
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopMode {
+    // stop at the last frame
+    Once,
+    Loop,
+    // 0 1 2 1 0 1 2 ...
+    PingPong,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub sprite: String,
+    pub index: usize,
+    pub duration: f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    frames: Vec<Frame>,
+    mode: LoopMode
+}
+
+impl Animation {
+    pub fn new(frames: Vec<Frame>, mode: LoopMode) -> Animation {
+        assert!(!frames.is_empty(), "Animation must have at least one frame");
+
+        Animation {
+            frames,
+            mode
+        }
+    }
+
+    // Single frame which never changes
+    pub fn still(sprite: &str) -> Animation {
+        Animation::new(vec![Frame { sprite: String::from(sprite), index: 0, duration: f64::INFINITY }], LoopMode::Once)
+    }
+
+    // Every frame of the sheet in order with the same duration
+    pub fn from_sheet(sprite: &str, frame_count: usize, frame_time: f64, mode: LoopMode) -> Animation {
+        let frames = (0..frame_count)
+            .map(|index| Frame { sprite: String::from(sprite), index, duration: frame_time })
+            .collect();
+
+        Animation::new(frames, mode)
+    }
+
+    // Frame shown at `time` seconds since the animation start
+    pub fn frame_at(&self, time: f64) -> &Frame {
+        let order = self.order();
+
+        let cycle: f64 = order.iter().map(|&i| self.frames[i].duration).sum();
+
+        let local_time = match self.mode {
+            LoopMode::Once => time,
+            LoopMode::Loop | LoopMode::PingPong => time % cycle,
+        };
+
+        let mut acc = 0.0;
+
+        for &i in &order {
+            acc += self.frames[i].duration;
+
+            if local_time < acc {
+                return &self.frames[i];
+            }
+        }
+
+        &self.frames[*order.last().unwrap()]
+    }
+
+    // Frame indices of one cycle
+    fn order(&self) -> Vec<usize> {
+        let forward = 0..self.frames.len();
+
+        match self.mode {
+            LoopMode::Once | LoopMode::Loop => forward.collect(),
+            // the first and the last frames are not repeated on the way back
+            LoopMode::PingPong => forward.chain((1..self.frames.len().saturating_sub(1)).rev()).collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clip {
+    Idle,
+    Hit,
+    Escape,
+}
+
+// Animations of one character
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationSet {
+    pub idle: Animation,
+    pub hit: Animation,
+    pub escape: Animation
+}
+
+impl AnimationSet {
+    // Looks for `<sprite>_idle`, `<sprite>_hit` and `<sprite>_escape` sheets,
+    // missing clips fall back to the still sprite, frame rate comes from the sheet name
+    pub fn from_atlas(atlas: &Atlas, sprite: &str) -> AnimationSet {
+        let clip = |suffix: &str, mode: LoopMode| {
+            let sheet = format!("{}_{}", sprite, suffix);
+
+            if atlas.contains(&sheet) {
+                let frame_time = atlas.frame_time(&sheet).unwrap_or(DEFAULT_FRAME_TIME);
+
+                Animation::from_sheet(&sheet, atlas.frame_count(&sheet), frame_time, mode)
+            } else {
+                Animation::still(sprite)
+            }
+        };
+
+        AnimationSet {
+            idle: clip("idle", LoopMode::PingPong),
+            hit: clip("hit", LoopMode::Once),
+            escape: clip("escape", LoopMode::Once)
+        }
+    }
+
+    pub fn get(&self, clip: Clip) -> &Animation {
+        match clip {
+            Clip::Idle => &self.idle,
+            Clip::Hit => &self.hit,
+            Clip::Escape => &self.escape
+        }
+    }
+}
+
+// Plays clips of one animation set
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    set: AnimationSet,
+    clip: Clip,
+    time: f64
+}
+
+impl AnimationPlayer {
+    pub fn new(set: AnimationSet) -> AnimationPlayer {
+        AnimationPlayer {
+            set,
+            clip: Clip::Idle,
+            time: 0.0
+        }
+    }
+
+    pub fn play(&mut self, clip: Clip) {
+        self.clip = clip;
+        self.time = 0.0;
+    }
+
+    pub fn advance(&mut self, dt: f64) {
+        self.time += dt;
+    }
+
+    pub fn frame(&self) -> &Frame {
+        self.set.get(self.clip).frame_at(self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // frames 0 1 2 of 1 second each
+    fn sheet(mode: LoopMode) -> Animation {
+        Animation::from_sheet("sheet", 3, 1.0, mode)
+    }
+
+    fn frames(animation: &Animation, times: &[f64]) -> Vec<usize> {
+        times.iter().map(|&time| animation.frame_at(time).index).collect()
+    }
+
+    #[test]
+    fn once_stops_at_the_last_frame() {
+        assert_eq!(frames(&sheet(LoopMode::Once), &[0.0, 0.5, 1.0, 2.9, 3.0, 100.0]), vec![0, 0, 1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn loop_wraps_to_the_first_frame() {
+        assert_eq!(frames(&sheet(LoopMode::Loop), &[0.0, 1.5, 2.5, 3.0, 4.0, 5.5, 30.5]), vec![0, 1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn ping_pong_turns_around_without_repeats() {
+        // 0 1 2 1 0 1 2 ...
+        let times: Vec<f64> = (0..8).map(|i| i as f64 + 0.5).collect();
+        assert_eq!(frames(&sheet(LoopMode::PingPong), &times), vec![0, 1, 2, 1, 0, 1, 2, 1]);
+
+        // two frames just alternate
+        let pair = Animation::from_sheet("pair", 2, 1.0, LoopMode::PingPong);
+        assert_eq!(frames(&pair, &[0.5, 1.5, 2.5, 3.5]), vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn frames_keep_their_durations() {
+        let frame = |index, duration| Frame { sprite: String::from("sheet"), index, duration };
+        let animation = Animation::new(vec![frame(0, 0.1), frame(1, 0.4)], LoopMode::Loop);
+
+        assert_eq!(frames(&animation, &[0.05, 0.1, 0.45, 0.55, 0.65]), vec![0, 1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn still_never_changes() {
+        assert_eq!(frames(&Animation::still("princess"), &[0.0, 1e6]), vec![0, 0]);
+    }
+
+    #[test]
+    fn player_restarts_the_clip() {
+        let mut set = AnimationSet {
+            idle: Animation::still("idle"),
+            hit: sheet(LoopMode::Once),
+            escape: Animation::still("escape")
+        };
+        set.hit.frames[0].sprite = String::from("hit");
+
+        let mut player = AnimationPlayer::new(set);
+        assert_eq!(player.frame().sprite, "idle");
+
+        player.play(Clip::Hit);
+        player.advance(1.5);
+        assert_eq!(player.frame().index, 1);
+
+        player.play(Clip::Hit);
+        assert_eq!((player.frame().sprite.as_str(), player.frame().index), ("hit", 0));
+    }
+}
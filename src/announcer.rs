@@ -0,0 +1,23 @@
+#[allow(unused_imports)]
+use crate::log;
+
+// Text for screen readers, written into aria-live region of the page
+#[derive(Debug)]
+pub struct Announcer {
+    // None if the page has no region
+    region: Option<web_sys::Element>
+}
+
+impl Announcer {
+    pub fn new(document: &web_sys::Document, id: &str) -> Announcer {
+        Announcer {
+            region: document.get_element_by_id(id)
+        }
+    }
+
+    pub fn announce(&self, text: &str) {
+        if let Some(region) = &self.region {
+            region.set_text_content(Some(text));
+        }
+    }
+}
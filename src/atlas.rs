@@ -0,0 +1,448 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::texture::*;
+
+use image::ImageReader;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+include!(concat!(env!("OUT_DIR"), "/sprites.rs"));
+
+// Empty pixels between sprites
+// with linear filtering texels next to the border are mixed with the neighbours
+const PADDING: u32 = 2;
+
+const MAX_SIZE: u32 = 4096;
+
+// Sprite area inside atlas in texture coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub u: f32,
+    pub v: f32,
+    pub w: f32,
+    pub h: f32
+}
+
+impl UvRect {
+    pub fn as_array(&self) -> [f32; 4] {
+        [self.u, self.v, self.w, self.h]
+    }
+}
+
+#[derive(Debug)]
+struct SpriteImage {
+    name: String,
+    frame: usize,
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    mask: Option<AlphaMask>
+}
+
+// Collects images and packs them into one rgba page
+#[derive(Debug, Default)]
+pub struct AtlasBuilder {
+    images: Vec<SpriteImage>,
+    // seconds per frame of sheets which set their frame rate
+    frame_times: HashMap<String, f64>,
+    // keep alpha of decoded pngs for hit tests
+    alpha_masks: bool
+}
+
+impl AtlasBuilder {
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            images: Vec::new(),
+            frame_times: HashMap::new(),
+            alpha_masks: false
+        }
+    }
+
+    // All sprites from src/textures/sprites
+    pub fn with_embedded_sprites() -> AtlasBuilder {
+        let mut builder = AtlasBuilder::new();
+        builder.alpha_masks = true;
+
+        for &(name, data, columns, rows, fps) in SPRITES {
+            builder.add_png_sheet(name, data, columns, rows);
+
+            if fps > 0 {
+                builder.set_frame_time(name, 1.0 / fps as f64);
+            }
+        }
+
+        builder
+    }
+
+    // Sheet is a grid of equal frames, frames are numbered row by row from the top left corner
+    pub fn add_png_sheet(&mut self, name: &str, raw_data: &[u8], columns: u32, rows: u32) {
+        let reader = ImageReader::new(Cursor::new(raw_data))
+            .with_guessed_format()
+            .expect("Cursor io never fails");
+
+        let image = reader.decode().expect("Failed to decode sprite").to_rgba8();
+
+        let (width, height) = image.dimensions();
+
+        let first = self.images.len();
+
+        self.add_rgba_sheet(name, &image.into_raw(), width, height, columns, rows);
+
+        if self.alpha_masks {
+            for image in self.images[first..].iter_mut() {
+                image.mask = Some(AlphaMask::from_rgba(&image.pixels, image.width, image.height));
+            }
+        }
+    }
+
+    pub fn set_frame_time(&mut self, name: &str, frame_time: f64) {
+        self.frame_times.insert(String::from(name), frame_time);
+    }
+
+    pub fn add_rgba(&mut self, name: &str, rgba_data: &[u8], width: u32, height: u32) {
+        self.add_rgba_sheet(name, rgba_data, width, height, 1, 1);
+    }
+
+    pub fn add_rgba_sheet(&mut self, name: &str, rgba_data: &[u8], width: u32, height: u32, columns: u32, rows: u32) {
+        assert_eq!(rgba_data.len(), (4*width*height) as usize, "Sprite {} has wrong data size", name);
+        assert!(width.is_multiple_of(columns) && height.is_multiple_of(rows), "Sheet {} is not divisible into {}x{} frames", name, columns, rows);
+
+        let (frame_width, frame_height) = (width / columns, height / rows);
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut pixels = Vec::with_capacity((4*frame_width*frame_height) as usize);
+
+                for y in row*frame_height..(row + 1)*frame_height {
+                    let start = (4*(y*width + column*frame_width)) as usize;
+
+                    pixels.extend_from_slice(&rgba_data[start..start + (4*frame_width) as usize]);
+                }
+
+                self.images.push(SpriteImage {
+                    name: String::from(name),
+                    frame: (row*columns + column) as usize,
+                    pixels,
+                    width: frame_width,
+                    height: frame_height,
+                    mask: None
+                });
+            }
+        }
+    }
+
+    // Shelf packing: images sorted by height are placed left to right,
+    // when the row is full the next one starts below the tallest image of the row
+    pub fn pack(mut self) -> AtlasPage {
+        self.images.sort_by(|a, b| b.height.cmp(&a.height).then(a.name.cmp(&b.name)).then(a.frame.cmp(&b.frame)));
+
+        let mut width = 64;
+
+        let positions = loop {
+            if let Some(positions) = Self::place(&self.images, width) {
+                break positions;
+            }
+
+            width *= 2;
+
+            assert!(width <= MAX_SIZE, "Sprites do not fit into {}x{} atlas", MAX_SIZE, MAX_SIZE);
+        };
+
+        let height = width;
+
+        let mut pixels = vec![0u8; (4*width*height) as usize];
+        let mut regions: HashMap<String, Vec<UvRect>> = HashMap::new();
+        let mut masks: HashMap<(String, usize), AlphaMask> = HashMap::new();
+
+        for (image, &(x, y)) in self.images.iter().zip(positions.iter()) {
+            for row in 0..image.height {
+                let src = (4*row*image.width) as usize;
+                let dst = (4*((y + row)*width + x)) as usize;
+                let len = (4*image.width) as usize;
+
+                pixels[dst..dst + len].copy_from_slice(&image.pixels[src..src + len]);
+            }
+
+            // shrink by half texel so edge samples never reach padding
+            let rect = UvRect {
+                u: (x as f32 + 0.5) / width as f32,
+                v: (y as f32 + 0.5) / height as f32,
+                w: (image.width as f32 - 1.0) / width as f32,
+                h: (image.height as f32 - 1.0) / height as f32
+            };
+
+            if let Some(mask) = &image.mask {
+                masks.insert((image.name.clone(), image.frame), mask.clone());
+            }
+
+            let frames = regions.entry(image.name.clone()).or_default();
+
+            if frames.len() <= image.frame {
+                frames.resize(image.frame + 1, rect);
+            }
+
+            frames[image.frame] = rect;
+        }
+
+        AtlasPage {
+            pixels,
+            width,
+            height,
+            regions,
+            masks,
+            frame_times: self.frame_times
+        }
+    }
+
+    fn place(images: &[SpriteImage], size: u32) -> Option<Vec<(u32, u32)>> {
+        let mut positions = Vec::with_capacity(images.len());
+
+        let (mut x, mut y, mut row_height) = (PADDING, PADDING, 0);
+
+        for image in images {
+            if image.width + 2*PADDING > size {
+                return None;
+            }
+
+            if x + image.width + PADDING > size {
+                x = PADDING;
+                y += row_height + PADDING;
+                row_height = 0;
+            }
+
+            if y + image.height + PADDING > size {
+                return None;
+            }
+
+            positions.push((x, y));
+
+            x += image.width + PADDING;
+            row_height = row_height.max(image.height);
+        }
+
+        Some(positions)
+    }
+}
+
+// Packed rgba data before upload to gpu
+#[derive(Debug)]
+pub struct AtlasPage {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub regions: HashMap<String, Vec<UvRect>>,
+    // sprite name and frame
+    pub masks: HashMap<(String, usize), AlphaMask>,
+    pub frame_times: HashMap<String, f64>
+}
+
+#[derive(Debug)]
+pub struct Atlas {
+    texture: Texture,
+    regions: HashMap<String, Vec<UvRect>>,
+    masks: HashMap<(String, usize), AlphaMask>,
+    frame_times: HashMap<String, f64>
+}
+
+impl Atlas {
+    pub fn new(gl: &web_sys::WebGl2RenderingContext, page: AtlasPage) -> Atlas {
+        let texture = from_atlas_data(gl, &page.pixels, page.width as i32, page.height as i32);
+
+        Atlas {
+            texture,
+            regions: page.regions,
+            masks: page.masks,
+            frame_times: page.frame_times
+        }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn region(&self, sprite: &str) -> UvRect {
+        self.frame(sprite, 0)
+    }
+
+    pub fn frame(&self, sprite: &str, frame: usize) -> UvRect {
+        self.frames(sprite)[frame]
+    }
+
+    pub fn frame_count(&self, sprite: &str) -> usize {
+        self.regions.get(sprite).map_or(0, |frames| frames.len())
+    }
+
+    pub fn mask(&self, sprite: &str, frame: usize) -> Option<&AlphaMask> {
+        self.masks.get(&(String::from(sprite), frame))
+    }
+
+    // Seconds per frame if the sheet sets its frame rate
+    pub fn frame_time(&self, sprite: &str) -> Option<f64> {
+        self.frame_times.get(sprite).copied()
+    }
+
+    pub fn contains(&self, sprite: &str) -> bool {
+        self.regions.contains_key(sprite)
+    }
+
+    fn frames(&self, sprite: &str) -> &[UvRect] {
+        self.regions.get(sprite).unwrap_or_else(|| panic!("Unknown sprite {}", sprite))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sprite filled with one color
+    fn add(builder: &mut AtlasBuilder, name: &str, width: u32, height: u32, color: u8) {
+        builder.add_rgba(name, &vec![color; (4*width*height) as usize], width, height);
+    }
+
+    // Pixel rects (x, y, width, height) of every frame back from texture coordinates
+    fn rects(page: &AtlasPage) -> Vec<(String, (u32, u32, u32, u32))> {
+        let mut rects = Vec::new();
+
+        for (name, frames) in page.regions.iter() {
+            for rect in frames {
+                let x = (rect.u*page.width as f32 - 0.5).round() as u32;
+                let y = (rect.v*page.height as f32 - 0.5).round() as u32;
+                let w = (rect.w*page.width as f32 + 1.0).round() as u32;
+                let h = (rect.h*page.height as f32 + 1.0).round() as u32;
+
+                rects.push((name.clone(), (x, y, w, h)));
+            }
+        }
+
+        rects
+    }
+
+    // Rects grown by the padding on every side must not touch each other or leave the page
+    fn assert_packed(page: &AtlasPage) {
+        let rects = rects(page);
+
+        for (name, (x, y, w, h)) in rects.iter() {
+            assert!(*x >= PADDING && *y >= PADDING, "{} at {:?} is in the border", name, (x, y));
+            assert!(x + w + PADDING <= page.width && y + h + PADDING <= page.height, "{} is out of the page", name);
+        }
+
+        for (i, (a, (ax, ay, aw, ah))) in rects.iter().enumerate() {
+            for (b, (bx, by, bw, bh)) in rects.iter().skip(i + 1) {
+                let apart = ax + aw + PADDING <= *bx || bx + bw + PADDING <= *ax || ay + ah + PADDING <= *by || by + bh + PADDING <= *ay;
+                assert!(apart, "{} and {} are closer than the padding", a, b);
+            }
+        }
+    }
+
+    fn images(sizes: &[(u32, u32)]) -> Vec<SpriteImage> {
+        sizes.iter()
+            .map(|&(width, height)| SpriteImage { name: String::new(), frame: 0, pixels: Vec::new(), width, height, mask: None })
+            .collect()
+    }
+
+    #[test]
+    fn packed_sprites_do_not_overlap() {
+        let mut builder = AtlasBuilder::new();
+
+        for i in 0..20 {
+            add(&mut builder, &format!("sprite{}", i), 5 + 3*(i % 7), 4 + 5*(i % 4), i as u8 + 1);
+        }
+
+        let page = builder.pack();
+
+        assert_eq!(page.regions.len(), 20);
+        assert_packed(&page);
+    }
+
+    #[test]
+    fn pixels_are_copied_and_padding_stays_empty() {
+        let mut builder = AtlasBuilder::new();
+        add(&mut builder, "a", 10, 6, 100);
+        add(&mut builder, "b", 7, 6, 200);
+
+        let page = builder.pack();
+        let pixel = |x: u32, y: u32| page.pixels[(4*(y*page.width + x)) as usize];
+
+        let rects = rects(&page);
+        let mut filled = 0;
+
+        for (name, (x, y, w, h)) in rects.iter() {
+            let color = if name == "a" { 100 } else { 200 };
+
+            assert!((*y..y + h).all(|py| (*x..x + w).all(|px| pixel(px, py) == color)));
+            filled += w*h;
+        }
+
+        // nothing is drawn outside the sprites
+        let drawn = (0..page.height).flat_map(|y| (0..page.width).map(move |x| (x, y))).filter(|&(x, y)| pixel(x, y) != 0).count();
+        assert_eq!(drawn as u32, filled);
+    }
+
+    #[test]
+    fn sheet_frames_are_packed_in_order() {
+        let mut builder = AtlasBuilder::new();
+
+        // 2x2 frames of 4x4, every frame has its own color
+        let mut data = vec![0u8; 4*8*8];
+        for y in 0..8 {
+            for x in 0..8 {
+                data[4*(y*8 + x)] = (1 + (y / 4)*2 + x / 4) as u8;
+            }
+        }
+
+        builder.add_rgba_sheet("sheet", &data, 8, 8, 2, 2);
+        builder.set_frame_time("sheet", 0.25);
+
+        let page = builder.pack();
+        assert_eq!(page.regions["sheet"].len(), 4);
+        assert_eq!(page.frame_times.get("sheet"), Some(&0.25));
+
+        for (frame, rect) in page.regions["sheet"].iter().enumerate() {
+            let x = (rect.u*page.width as f32 - 0.5).round() as u32;
+            let y = (rect.v*page.height as f32 - 0.5).round() as u32;
+
+            assert_eq!(page.pixels[(4*(y*page.width + x)) as usize], frame as u8 + 1);
+        }
+
+        assert_packed(&page);
+    }
+
+    #[test]
+    fn page_grows_when_sprites_do_not_fit() {
+        let mut small = AtlasBuilder::new();
+        add(&mut small, "a", 10, 10, 1);
+        assert_eq!(small.pack().width, 64);
+
+        // wider than the first page with its padding
+        let mut wide = AtlasBuilder::new();
+        add(&mut wide, "a", 61, 10, 1);
+        let page = wide.pack();
+        assert_eq!((page.width, page.height), (128, 128));
+        assert_packed(&page);
+
+        // every sprite fits, but not all of them on one page
+        let mut many = AtlasBuilder::new();
+        for i in 0..9 {
+            add(&mut many, &format!("sprite{}", i), 20, 20, 1);
+        }
+        let page = many.pack();
+        assert_eq!(page.width, 128);
+        assert_packed(&page);
+    }
+
+    #[test]
+    fn place_goes_in_rows() {
+        // rows of two on a 32 page: 2 + 12 + 2 + 12 + 2 = 30
+        let positions = AtlasBuilder::place(&images(&[(12, 10), (12, 8), (12, 5)]), 32);
+        assert_eq!(positions, Some(vec![(2, 2), (16, 2), (2, 14)]));
+
+        // the next row starts below the tallest sprite of the row
+        assert_eq!(AtlasBuilder::place(&images(&[(12, 12), (12, 12), (12, 12)]), 30), Some(vec![(2, 2), (16, 2), (2, 16)]));
+        // one sprite per row does not fit the height
+        assert_eq!(AtlasBuilder::place(&images(&[(12, 12), (12, 12), (12, 12)]), 29), None);
+
+        // no room for the padding
+        assert_eq!(AtlasBuilder::place(&images(&[(29, 10)]), 32), None);
+        assert_eq!(AtlasBuilder::place(&images(&[(28, 10)]), 32), Some(vec![(2, 2)]));
+    }
+}
@@ -0,0 +1,228 @@
+use wasm_bindgen::prelude::*;
+
+#[allow(unused_imports)]
+use crate::log;
+use crate::synth::{self, SynthParams};
+use crate::events::*;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// Only effects are synthesized, the music loop stays recorded
+const MUSIC: &[u8] = include_bytes!("sounds/music.wav");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    PopUp,
+    Hit,
+    Miss,
+    DecoyHit,
+    // special target is hit
+    Bonus,
+    Explosion,
+}
+
+const SOUNDS: [Sound; 6] = [Sound::PopUp, Sound::Hit, Sound::Miss, Sound::DecoyHit, Sound::Bonus, Sound::Explosion];
+
+impl Sound {
+    // Effects are synthesized on start instead of shipping audio files
+    fn params(self) -> SynthParams {
+        match self {
+            Sound::PopUp => synth::pop_up(),
+            Sound::Hit => synth::hit(),
+            Sound::Miss => synth::miss(),
+            Sound::DecoyHit => synth::decoy_hit(),
+            Sound::Bonus => synth::bonus(),
+            Sound::Explosion => synth::explosion()
+        }
+    }
+}
+
+// Volume groups, every group has its own gain before the master one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    Master,
+    Effects,
+    Music,
+}
+
+// Music is decoded in background and stored here when ready
+type MusicBuffer = Rc<RefCell<Option<web_sys::AudioBuffer>>>;
+
+// Browsers start audio context suspended until the first user gesture,
+// sounds played before `unlock` are dropped
+#[derive(Debug)]
+pub struct Audio {
+    ctx: web_sys::AudioContext,
+    master: web_sys::GainNode,
+    effects: web_sys::GainNode,
+    music: web_sys::GainNode,
+    buffers: HashMap<Sound, web_sys::AudioBuffer>,
+    music_buffer: MusicBuffer,
+    music_source: Option<web_sys::AudioBufferSourceNode>,
+    master_volume: f32,
+    unlocked: bool,
+    muted: bool
+}
+
+impl Audio {
+    pub fn new() -> Audio {
+        let ctx = web_sys::AudioContext::new().expect("Failed to create AudioContext");
+
+        let gain = || ctx.create_gain().expect("Failed to create gain node");
+
+        let master = gain();
+        let effects = gain();
+        let music = gain();
+
+        master.connect_with_audio_node(&ctx.destination()).expect("Failed to connect gain node");
+        effects.connect_with_audio_node(&master).expect("Failed to connect gain node");
+        music.connect_with_audio_node(&master).expect("Failed to connect gain node");
+
+        let buffers = SOUNDS.iter()
+            .map(|&sound| (sound, synthesize(&ctx, &sound.params())))
+            .collect();
+
+        let music_buffer: MusicBuffer = Rc::new(RefCell::new(None));
+
+        {
+            let music_buffer = music_buffer.clone();
+
+            decode(&ctx, MUSIC, move |buffer| {
+                *music_buffer.borrow_mut() = Some(buffer);
+            });
+        }
+
+        let mut audio = Audio {
+            ctx,
+            master,
+            effects,
+            music,
+            buffers,
+            music_buffer,
+            music_source: None,
+            master_volume: 1.0,
+            unlocked: false,
+            muted: false
+        };
+
+        audio.set_volume(Group::Music, 0.5);
+
+        audio
+    }
+
+    // Must be called from user gesture handler, e.g. pointerdown or keydown
+    pub fn unlock(&mut self) {
+        if self.unlocked {
+            return;
+        }
+
+        let _ = self.ctx.resume();
+
+        // iOS Safari needs something played inside the gesture
+        if let Ok(buffer) = self.ctx.create_buffer(1, 1, 22050.0) {
+            self.play_buffer(&buffer, &self.effects, false);
+        }
+
+        self.unlocked = true;
+    }
+
+    pub fn play(&self, sound: Sound) {
+        if !self.unlocked {
+            return;
+        }
+
+        if let Some(buffer) = self.buffers.get(&sound) {
+            self.play_buffer(buffer, &self.effects, false);
+        }
+    }
+
+    // Music is decoded
+    pub fn is_loaded(&self) -> bool {
+        self.music_buffer.borrow().is_some()
+    }
+
+    // Starts music loop once it is decoded and audio is unlocked
+    pub fn update(&mut self) {
+        if !self.unlocked || self.music_source.is_some() {
+            return;
+        }
+
+        if let Some(buffer) = self.music_buffer.borrow().as_ref() {
+            self.music_source = self.play_buffer(buffer, &self.music, true);
+        }
+    }
+
+    // 0 is silent and 1 is full volume
+    pub fn set_volume(&mut self, group: Group, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+
+        match group {
+            Group::Master => {
+                self.master_volume = volume;
+                self.apply_master();
+            },
+            Group::Effects => self.effects.gain().set_value(volume),
+            Group::Music => self.music.gain().set_value(volume)
+        }
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_master();
+    }
+
+    fn apply_master(&self) {
+        self.master.gain().set_value(if self.muted { 0.0 } else { self.master_volume });
+    }
+
+    fn play_buffer(&self, buffer: &web_sys::AudioBuffer, output: &web_sys::GainNode, looped: bool) -> Option<web_sys::AudioBufferSourceNode> {
+        let source = self.ctx.create_buffer_source().ok()?;
+
+        source.set_buffer(Some(buffer));
+        source.set_loop(looped);
+        source.connect_with_audio_node(output).ok()?;
+        source.start().ok()?;
+
+        Some(source)
+    }
+}
+
+impl Subscriber for Audio {
+    fn on_event(&mut self, event: &GameEvent, _follow_ups: &mut Vec<GameEvent>) {
+        match event {
+            GameEvent::TargetSpawned { .. } => self.play(Sound::PopUp),
+            GameEvent::TargetHit { special: Some(_), .. } => self.play(Sound::Bonus),
+            GameEvent::TargetHit { .. } => self.play(Sound::Hit),
+            GameEvent::DecoyHit => self.play(Sound::DecoyHit),
+            GameEvent::BombHit => self.play(Sound::Explosion),
+            GameEvent::TargetMissed => self.play(Sound::Miss),
+            _ => {}
+        }
+    }
+}
+
+fn synthesize(ctx: &web_sys::AudioContext, params: &SynthParams) -> web_sys::AudioBuffer {
+    let sample_rate = ctx.sample_rate();
+    let samples = synth::render(params, sample_rate as u32);
+
+    // empty buffers are not allowed
+    let buffer = ctx.create_buffer(1, samples.len().max(1) as u32, sample_rate).expect("Failed to create audio buffer");
+    buffer.copy_to_channel(&samples, 0).expect("Failed to fill audio buffer");
+
+    buffer
+}
+
+fn decode<F: FnOnce(web_sys::AudioBuffer) + 'static>(ctx: &web_sys::AudioContext, data: &[u8], on_decoded: F) {
+    let array = js_sys::Uint8Array::from(data).buffer();
+
+    let callback = Closure::once_into_js(on_decoded);
+
+    // buffer comes to the callback, promise is not needed
+    let _ = ctx.decode_audio_data_with_success_callback(&array, callback.unchecked_ref()).expect("Failed to decode audio data");
+}
@@ -0,0 +1,320 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::math::*;
+use crate::shape::Shape;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub a: Vec2,
+    pub b: Vec2
+}
+
+impl Segment {
+    pub fn new(a: Vec2, b: Vec2) -> Segment {
+        Segment { a, b }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.a, self.b)
+    }
+
+    // Proper crossing or touching
+    pub fn intersects(&self, other: &Segment) -> bool {
+        let d1 = orientation(other.a, other.b, self.a);
+        let d2 = orientation(other.a, other.b, self.b);
+        let d3 = orientation(self.a, self.b, other.a);
+        let d4 = orientation(self.a, self.b, other.b);
+
+        if d1*d2 < 0.0 && d3*d4 < 0.0 {
+            return true;
+        }
+
+        (d1 == 0.0 && on_segment(other, self.a))
+            || (d2 == 0.0 && on_segment(other, self.b))
+            || (d3 == 0.0 && on_segment(self, other.a))
+            || (d4 == 0.0 && on_segment(self, other.b))
+    }
+}
+
+fn orientation(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b - a).cross(p - a)
+}
+
+// p is known to be collinear with the segment
+fn on_segment(segment: &Segment, p: Vec2) -> bool {
+    Aabb::new(segment.a, segment.b).contains(p)
+}
+
+// Volume used for overlap queries
+// polygons must be convex, vertices go in any direction, a segment is a polygon too
+// polygon without vertices is empty and overlaps nothing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Volume {
+    Aabb(Aabb),
+    Circle(Circle),
+    Polygon(Vec<Vec2>),
+}
+
+impl Volume {
+    // None for empty polygon
+    pub fn bounds(&self) -> Option<Aabb> {
+        match self {
+            Volume::Aabb(aabb) => Some(*aabb),
+            Volume::Circle(circle) => {
+                let r = Vec2::new(circle.radius, circle.radius);
+                Some(Aabb::new(circle.center - r, circle.center + r))
+            },
+            Volume::Polygon(points) => Aabb::from_points(points)
+        }
+    }
+
+    pub fn overlaps(&self, other: &Volume) -> bool {
+        if !self.bounds().zip(other.bounds()).is_some_and(|(a, b)| a.intersects(&b)) {
+            return false;
+        }
+
+        match (self, other) {
+            (Volume::Aabb(_), Volume::Aabb(_)) => true,
+            (Volume::Circle(a), Volume::Circle(b)) => a.center.distance(b.center) <= a.radius + b.radius,
+            (Volume::Circle(circle), polygon) | (polygon, Volume::Circle(circle)) => {
+                polygon.vertices().is_some_and(|points| circle_polygon(circle, &points))
+            },
+            (a, b) => a.vertices().zip(b.vertices()).is_some_and(|(a, b)| sat(&a, &b))
+        }
+    }
+
+    // Polygon outline, None for circles
+    fn vertices(&self) -> Option<Vec<Vec2>> {
+        match self {
+            Volume::Aabb(aabb) => Some(vec![aabb.min, Vec2::new(aabb.max.x(), aabb.min.y()), aabb.max, Vec2::new(aabb.min.x(), aabb.max.y())]),
+            Volume::Polygon(points) => Some(points.clone()),
+            Volume::Circle(_) => None
+        }
+    }
+}
+
+fn edges(points: &[Vec2]) -> impl Iterator<Item = Segment> + '_ {
+    (0..points.len()).map(move |i| Segment::new(points[i], points[(i + 1) % points.len()]))
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| {
+        let d = p.dot(axis);
+        (min.min(d), max.max(d))
+    })
+}
+
+// Separating axis theorem: convex polygons do not overlap
+// if their projections on some edge normal are disjoint
+fn sat(a: &[Vec2], b: &[Vec2]) -> bool {
+    edges(a).chain(edges(b)).all(|edge| {
+        let axis = (edge.b - edge.a).perp();
+
+        let (min_a, max_a) = project(a, axis);
+        let (min_b, max_b) = project(b, axis);
+
+        min_a <= max_b && min_b <= max_a
+    })
+}
+
+// Edge normals plus axis from the closest vertex to the circle center
+fn circle_polygon(circle: &Circle, points: &[Vec2]) -> bool {
+    let Some(closest) = points.iter()
+        .copied()
+        .min_by(|p, q| p.distance(circle.center).total_cmp(&q.distance(circle.center))) else {
+        return false;
+    };
+
+    let axes = edges(points).map(|edge| (edge.b - edge.a).perp().normalize())
+        .chain(std::iter::once((closest - circle.center).normalize()));
+
+    for axis in axes {
+        if axis == Vec2::zero() {
+            continue;
+        }
+
+        let (min, max) = project(points, axis);
+        let center = circle.center.dot(axis);
+
+        if center + circle.radius < min || max < center - circle.radius {
+            return false;
+        }
+    }
+
+    true
+}
+
+// Segment against any simple outline, e.g. figure silhouette
+pub fn segment_hits_shape(segment: &Segment, shape: &Shape) -> bool {
+    if !shape.bounds().is_some_and(|bounds| bounds.intersects(&segment.bounds())) {
+        return false;
+    }
+
+    shape.contains(segment.a) || shape.contains(segment.b) || edges(&shape.vertices).any(|edge| edge.intersects(segment))
+}
+
+// Broad phase: items are stored in every cell their bounds touch
+#[derive(Debug)]
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<T>>
+}
+
+impl<T: Copy + PartialEq> SpatialGrid<T> {
+    pub fn new(cell_size: f32) -> SpatialGrid<T> {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new()
+        }
+    }
+
+    pub fn insert(&mut self, item: T, bounds: &Aabb) {
+        for cell in self.cells_of(bounds) {
+            self.cells.entry(cell).or_default().push(item);
+        }
+    }
+
+    // Candidates which may overlap the area, every item is returned once
+    pub fn query(&self, area: &Aabb) -> Vec<T> {
+        let mut result: Vec<T> = Vec::new();
+
+        for cell in self.cells_of(area) {
+            for &item in self.cells.get(&cell).into_iter().flatten() {
+                if !result.contains(&item) {
+                    result.push(item);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn cells_of(&self, bounds: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+
+        let (x0, x1) = (cell(bounds.min.x()), cell(bounds.max.x()));
+        let (y0, y1) = (cell(bounds.min.y()), cell(bounds.max.y()));
+
+        (x0..=x1).flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(x: f32, y: f32, radius: f32) -> Volume {
+        Volume::Circle(Circle { center: Vec2::new(x, y), radius })
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Volume {
+        Volume::Aabb(Aabb::new(Vec2::new(x, y), Vec2::new(x + size, y + size)))
+    }
+
+    fn triangle(offset: f32) -> Volume {
+        Volume::Polygon(vec![Vec2::new(offset, 0.0), Vec2::new(offset + 1.0, 0.0), Vec2::new(offset, 1.0)])
+    }
+
+    #[test]
+    fn circles_overlap() {
+        assert!(circle(0.0, 0.0, 1.0).overlaps(&circle(1.5, 0.0, 1.0)));
+        assert!(circle(0.0, 0.0, 1.0).overlaps(&circle(2.0, 0.0, 1.0)));
+        assert!(!circle(0.0, 0.0, 1.0).overlaps(&circle(2.1, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn circle_and_polygon_overlap() {
+        assert!(circle(0.2, 0.2, 0.1).overlaps(&triangle(0.0)));
+        assert!(triangle(0.0).overlaps(&circle(-0.5, 0.5, 0.6)));
+        // bounds overlap, the hypotenuse separates them
+        assert!(!circle(0.9, 0.9, 0.3).overlaps(&triangle(0.0)));
+        assert!(circle(0.5, 0.5, 0.0).overlaps(&square(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn sat_separates_polygons() {
+        assert!(triangle(0.0).overlaps(&triangle(0.5)));
+        // bounds overlap, the hypotenuse separates them
+        assert!(!triangle(0.0).overlaps(&Volume::Polygon(vec![Vec2::new(1.0, 1.0), Vec2::new(0.6, 1.0), Vec2::new(1.0, 0.6)])));
+        assert!(!square(0.0, 0.0, 1.0).overlaps(&triangle(1.5)));
+    }
+
+    #[test]
+    fn sat_touching_polygons_overlap() {
+        assert!(triangle(0.0).overlaps(&triangle(1.0)));
+        assert!(square(0.0, 0.0, 1.0).overlaps(&Volume::Polygon(vec![Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0)])));
+    }
+
+    #[test]
+    fn segment_overlaps_box() {
+        let segment = |ax, ay, bx, by| Volume::Polygon(vec![Vec2::new(ax, ay), Vec2::new(bx, by)]);
+
+        assert!(segment(-1.0, 0.5, 2.0, 0.5).overlaps(&square(0.0, 0.0, 1.0)));
+        assert!(!segment(0.6, 1.5, 1.5, 0.6).overlaps(&square(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn empty_polygon_overlaps_nothing() {
+        let empty = Volume::Polygon(Vec::new());
+
+        assert_eq!(empty.bounds(), None);
+        assert!(!empty.overlaps(&empty));
+
+        for other in [circle(0.0, 0.0, 1.0), square(-1.0, -1.0, 2.0), triangle(0.0)] {
+            assert!(!empty.overlaps(&other));
+            assert!(!other.overlaps(&empty));
+        }
+
+        assert!(!circle_polygon(&Circle { center: Vec2::zero(), radius: 1.0 }, &[]));
+    }
+
+    #[test]
+    fn segments_intersect() {
+        let segment = Segment::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0));
+
+        assert!(segment.intersects(&Segment::new(Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0))));
+        // touching at the end
+        assert!(segment.intersects(&Segment::new(Vec2::new(1.0, 1.0), Vec2::new(2.0, 0.0))));
+        assert!(!segment.intersects(&Segment::new(Vec2::new(0.0, 1.0), Vec2::new(-1.0, 2.0))));
+    }
+
+    #[test]
+    fn segment_hits_shape_outline() {
+        let shape = Shape::square("square");
+
+        // crossing the outline, inside and away from it
+        assert!(segment_hits_shape(&Segment::new(Vec2::new(-2.0, 0.0), Vec2::new(2.0, 0.0)), &shape));
+        assert!(segment_hits_shape(&Segment::new(Vec2::new(-0.1, 0.0), Vec2::new(0.1, 0.0)), &shape));
+        assert!(!segment_hits_shape(&Segment::new(Vec2::new(-2.0, 2.0), Vec2::new(2.0, 2.0)), &shape));
+        // bounds overlap, the segment passes the corner
+        assert!(!segment_hits_shape(&Segment::new(Vec2::new(0.5, 1.6), Vec2::new(1.6, 0.5)), &shape));
+    }
+
+    #[test]
+    fn grid_queries_across_cells() {
+        let mut grid = SpatialGrid::new(1.0);
+
+        // spans four cells
+        grid.insert(1, &Aabb::new(Vec2::new(0.5, 0.5), Vec2::new(1.5, 1.5)));
+        grid.insert(2, &Aabb::new(Vec2::new(3.2, 3.2), Vec2::new(3.4, 3.4)));
+        grid.insert(3, &Aabb::new(Vec2::new(-1.5, -1.5), Vec2::new(-1.2, -1.2)));
+
+        assert_eq!(grid.query(&Aabb::new(Vec2::new(1.2, 1.2), Vec2::new(1.3, 1.3))), vec![1]);
+        assert_eq!(grid.query(&Aabb::new(Vec2::new(0.1, 0.1), Vec2::new(0.2, 0.2))), vec![1]);
+
+        // every item once, even when it is in several cells of the area
+        let mut found = grid.query(&Aabb::new(Vec2::new(-2.0, -2.0), Vec2::new(3.5, 3.5)));
+        found.sort();
+        assert_eq!(found, vec![1, 2, 3]);
+
+        assert!(grid.query(&Aabb::new(Vec2::new(5.0, 5.0), Vec2::new(6.0, 6.0))).is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::special::Special;
+use crate::transform::Transform;
+use crate::tween::*;
+use crate::animation::Clip;
+
+// Index of entity and generation of the slot
+// so stale handles of despawned entities never match new ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: usize,
+    generation: u32
+}
+
+// Components of one type indexed by entity
+#[derive(Debug)]
+pub struct Storage<T> {
+    items: Vec<Option<(u32, T)>>
+}
+
+#[allow(unused)]
+impl<T> Storage<T> {
+    pub fn new() -> Storage<T> {
+        Storage {
+            items: Vec::new()
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        if self.items.len() <= entity.index {
+            self.items.resize_with(entity.index + 1, || None);
+        }
+
+        self.items[entity.index] = Some((entity.generation, component));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        self.get(entity)?;
+
+        self.items[entity.index].take().map(|(_, component)| component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.items.get(entity.index) {
+            Some(Some((generation, component))) if *generation == entity.generation => Some(component),
+            _ => None
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.items.get_mut(entity.index) {
+            Some(Some((generation, component))) if *generation == entity.generation => Some(component),
+            _ => None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.items.iter().enumerate().filter_map(|(index, item)| {
+            item.as_ref().map(|(generation, component)| (Entity { index, generation: *generation }, component))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.items.iter_mut().enumerate().filter_map(|(index, item)| {
+            item.as_mut().map(|(generation, component)| (Entity { index, generation: *generation }, component))
+        })
+    }
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage::new()
+    }
+}
+
+// Drawn by the scene figure with this index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Figure(pub usize);
+
+// Clip the figure animation plays, the scene player restarts when another one is asked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    clip: Clip,
+    // not passed to the scene yet
+    restart: bool
+}
+
+impl Sprite {
+    pub fn new() -> Sprite {
+        Sprite {
+            clip: Clip::Idle,
+            restart: false
+        }
+    }
+
+    pub fn clip(&self) -> Clip {
+        self.clip
+    }
+
+    pub fn play(&mut self, clip: Clip) {
+        self.clip = clip;
+        self.restart = true;
+    }
+
+    // true once after every `play`
+    pub fn take_restart(&mut self) -> bool {
+        std::mem::take(&mut self.restart)
+    }
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Sprite::new()
+    }
+}
+
+// Entity reacts to clicks inside its figure outline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetKind {
+    // must be hit
+    Target,
+    // must not be hit
+    Decoy,
+    // must not be hit, ends the round
+    Bomb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub kind: TargetKind
+}
+
+// Systems in `systems.rs` run over the components every frame
+// and pass transforms, props and clips to the scene figures
+#[derive(Debug, Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<usize>,
+
+    pub figures: Storage<Figure>,
+    // placement of the figure in its hole
+    pub transforms: Storage<Transform>,
+    pub sprites: Storage<Sprite>,
+    // animated on top of the transform by tweens
+    pub props: Storage<Props>,
+    pub tweens: Storage<Tweener>,
+    pub colliders: Storage<Collider>,
+    pub targets: Storage<Target>,
+    pub specials: Storage<Special>
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                self.generations.len() - 1
+            }
+        };
+
+        self.alive[index] = true;
+
+        Entity {
+            index,
+            generation: self.generations[index]
+        }
+    }
+
+    // Figures live as long as the game now, despawn is there for short lived entities
+    #[allow(unused)]
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+
+        self.figures.remove(entity);
+        self.transforms.remove(entity);
+        self.sprites.remove(entity);
+        self.props.remove(entity);
+        self.tweens.remove(entity);
+        self.colliders.remove(entity);
+        self.targets.remove(entity);
+        self.specials.remove(entity);
+
+        self.alive[entity.index] = false;
+        self.generations[entity.index] += 1;
+        self.free.push(entity.index);
+    }
+
+    #[allow(unused)]
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity.index).copied().unwrap_or(false) && self.generations[entity.index] == entity.generation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawned_entities_get_their_components() {
+        let mut world = World::new();
+
+        let a = world.spawn();
+        let b = world.spawn();
+        assert_ne!(a, b);
+        assert!(world.is_alive(a) && world.is_alive(b));
+
+        world.figures.insert(a, Figure(0));
+        world.targets.insert(b, Target { kind: TargetKind::Decoy });
+
+        assert_eq!(world.figures.get(a), Some(&Figure(0)));
+        assert_eq!(world.figures.get(b), None);
+        assert_eq!(world.targets.get(b).map(|target| target.kind), Some(TargetKind::Decoy));
+    }
+
+    #[test]
+    fn despawn_drops_components_and_stale_handles() {
+        let mut world = World::new();
+
+        let old = world.spawn();
+        world.figures.insert(old, Figure(0));
+        world.sprites.insert(old, Sprite::new());
+        world.despawn(old);
+
+        assert!(!world.is_alive(old));
+        assert_eq!(world.figures.get(old), None);
+        assert_eq!(world.sprites.get(old), None);
+
+        // the slot is reused with a new generation
+        let new = world.spawn();
+        world.figures.insert(new, Figure(1));
+
+        assert_eq!(new.index, old.index);
+        assert!(world.is_alive(new) && !world.is_alive(old));
+        assert_eq!(world.figures.get(old), None);
+        assert_eq!(world.figures.remove(old), None);
+        assert_eq!(world.figures.get(new), Some(&Figure(1)));
+
+        // despawning twice does nothing
+        world.despawn(old);
+        assert!(world.is_alive(new));
+    }
+
+    #[test]
+    fn query_goes_over_entities_with_the_component() {
+        let mut world = World::new();
+
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+
+        for (i, &entity) in entities.iter().enumerate() {
+            world.figures.insert(entity, Figure(i));
+        }
+
+        world.colliders.insert(entities[1], Collider);
+        world.colliders.insert(entities[3], Collider);
+        world.despawn(entities[3]);
+
+        let colliding: Vec<Figure> = world.colliders.iter()
+            .filter_map(|(entity, _)| world.figures.get(entity).copied())
+            .collect();
+        assert_eq!(colliding, vec![Figure(1)]);
+
+        for (_, figure) in world.figures.iter_mut() {
+            figure.0 += 10;
+        }
+
+        let figures: Vec<(Entity, Figure)> = world.figures.iter().map(|(entity, &figure)| (entity, figure)).collect();
+        assert_eq!(figures, vec![(entities[0], Figure(10)), (entities[1], Figure(11)), (entities[2], Figure(12))]);
+    }
+
+    #[test]
+    fn sprite_restarts_once_per_play() {
+        let mut sprite = Sprite::new();
+        assert!(!sprite.take_restart());
+
+        sprite.play(Clip::Hit);
+        assert_eq!(sprite.clip(), Clip::Hit);
+        assert!(sprite.take_restart());
+        assert!(!sprite.take_restart());
+    }
+}
@@ -0,0 +1,148 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::game_mode::ModeKind;
+use crate::scoring::HitScore;
+use crate::special::Special;
+use crate::ecs::TargetKind;
+use crate::achievements::Achievement;
+
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+// What happens in the game, systems like audio and achievements react to them
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    GameStarted { mode: ModeKind },
+    // unfinished game from the save goes on
+    GameResumed { mode: ModeKind, score: u64 },
+    // figure starts rising in the hole
+    TargetSpawned { hole: usize, kind: TargetKind, special: Option<Special> },
+    // `score` is the game score after the hit
+    TargetHit { hit: HitScore, special: Option<Special>, score: u64 },
+    DecoyHit,
+    BombHit,
+    // target went down without being hit
+    TargetMissed,
+    NewBest { score: u64 },
+    AchievementUnlocked { achievement: &'static Achievement },
+    GameOver { mode: ModeKind, score: u64 },
+}
+
+pub trait Subscriber: Debug {
+    // Events pushed to `follow_ups` are published after this one
+    fn on_event(&mut self, event: &GameEvent, follow_ups: &mut Vec<GameEvent>);
+}
+
+// Delivers events to every subscriber right away, in the order they subscribed
+#[derive(Debug, Default)]
+pub struct EventBus {
+    subscribers: Vec<Rc<RefCell<dyn Subscriber>>>
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus {
+            subscribers: Vec::new()
+        }
+    }
+
+    pub fn subscribe(&mut self, subscriber: Rc<RefCell<dyn Subscriber>>) {
+        self.subscribers.push(subscriber);
+    }
+
+    // The event and its follow-ups in the order they were delivered
+    pub fn publish(&mut self, event: GameEvent) -> Vec<GameEvent> {
+        let mut queue = VecDeque::from([event]);
+        let mut published = Vec::new();
+
+        while let Some(event) = queue.pop_front() {
+            let mut follow_ups = Vec::new();
+
+            for subscriber in self.subscribers.iter() {
+                subscriber.borrow_mut().on_event(&event, &mut follow_ups);
+            }
+
+            queue.extend(follow_ups);
+            published.push(event);
+        }
+
+        published
+    }
+}
+
+// Writes every event to the console, enabled with `?log_events=1`
+// a stand-in for analytics
+#[derive(Debug)]
+pub struct EventLog;
+
+impl Subscriber for EventLog {
+    fn on_event(&mut self, event: &GameEvent, _follow_ups: &mut Vec<GameEvent>) {
+        log::write_debug(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes down what it got, next to what the other subscriber got
+    #[derive(Debug)]
+    struct Recorder {
+        name: &'static str,
+        seen: Rc<RefCell<Vec<(&'static str, GameEvent)>>>
+    }
+
+    impl Subscriber for Recorder {
+        fn on_event(&mut self, event: &GameEvent, _follow_ups: &mut Vec<GameEvent>) {
+            self.seen.borrow_mut().push((self.name, event.clone()));
+        }
+    }
+
+    // Answers a miss with a decoy hit and a bomb hit, and a decoy hit with a new best
+    #[derive(Debug)]
+    struct Chain {
+        seen: Rc<RefCell<Vec<(&'static str, GameEvent)>>>
+    }
+
+    impl Subscriber for Chain {
+        fn on_event(&mut self, event: &GameEvent, follow_ups: &mut Vec<GameEvent>) {
+            self.seen.borrow_mut().push(("chain", event.clone()));
+
+            match event {
+                GameEvent::TargetMissed => follow_ups.extend([GameEvent::DecoyHit, GameEvent::BombHit]),
+                GameEvent::DecoyHit => follow_ups.push(GameEvent::NewBest { score: 1 }),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn publishes_breadth_first_in_subscription_order() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        let mut bus = EventBus::new();
+        bus.subscribe(Rc::new(RefCell::new(Chain { seen: seen.clone() })));
+        bus.subscribe(Rc::new(RefCell::new(Recorder { name: "recorder", seen: seen.clone() })));
+
+        let published = bus.publish(GameEvent::TargetMissed);
+
+        // the follow-up of the decoy hit goes after the bomb hit
+        let order = vec![GameEvent::TargetMissed, GameEvent::DecoyHit, GameEvent::BombHit, GameEvent::NewBest { score: 1 }];
+        assert_eq!(published, order);
+
+        // every event reaches both subscribers before the next one
+        let expected: Vec<(&str, GameEvent)> = order.into_iter()
+            .flat_map(|event| [("chain", event.clone()), ("recorder", event)])
+            .collect();
+        assert_eq!(*seen.borrow(), expected);
+    }
+
+    #[test]
+    fn events_without_subscribers_are_published() {
+        let mut bus = EventBus::new();
+
+        assert_eq!(bus.publish(GameEvent::DecoyHit), vec![GameEvent::DecoyHit]);
+    }
+}
@@ -6,17 +6,193 @@ use crate::shape::*;
 use crate::point::*;
 use crate::ui::*;
 use crate::game_state::*;
+use crate::game_mode::*;
 use crate::scene::*;
+use crate::animation::Clip;
+use crate::tween::*;
+use crate::ecs::*;
+use crate::systems::*;
+use crate::gesture::*;
+use crate::keyboard::*;
+use crate::announcer::*;
+use crate::gamepad::*;
+use crate::audio::*;
+use crate::persistence::*;
+use crate::leaderboard::*;
+use crate::special::*;
+use crate::rand::*;
+use crate::util::query_value;
+use crate::spawner::*;
+use crate::events::*;
+use crate::achievements::*;
+use crate::haptics::*;
+use crate::screen::*;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+// Figure appears with a small overshoot
+fn pop_up() -> Sequence {
+    Sequence::new()
+        .then(Tween::from_to(Property::Scale, 0.6, 1.0, 0.35, Easing::BackOut))
+        .with(Tween::from_to(Property::Alpha, 0.0, 1.0, 0.15, Easing::QuadOut))
+}
+
+fn shake() -> Sequence {
+    Sequence::new()
+        .then(Tween::to(Property::OffsetX, 0.12, 0.04, Easing::QuadOut))
+        .then(Tween::to(Property::OffsetX, -0.12, 0.08, Easing::SineInOut))
+        .then(Tween::to(Property::OffsetX, 0.06, 0.06, Easing::SineInOut))
+        .then(Tween::to(Property::OffsetX, 0.0, 0.04, Easing::QuadIn))
+}
+
+fn hit_effect() -> Sequence {
+    Sequence::new()
+        .then(Tween::from_to(Property::Scale, 0.5, 1.6, 0.3, Easing::CubicOut))
+        .with(Tween::from_to(Property::Alpha, 1.0, 0.0, 0.3, Easing::QuadIn))
+}
+
+// Lines of the score breakdown go up one after another
+const POPUP_DELAY: f64 = 0.2;
+
+fn score_popup(line: usize) -> Sequence {
+    Sequence::new()
+        .wait(line as f64*POPUP_DELAY)
+        .then(Tween::from_to(Property::OffsetY, 0.0, 1.2, 0.6, Easing::CubicOut))
+        .with(Tween::from_to(Property::Alpha, 1.0, 0.0, 0.6, Easing::QuadIn))
+}
+
+fn explosion() -> Sequence {
+    Sequence::new()
+        .then(Tween::from_to(Property::Scale, 0.8, 3.0, 0.4, Easing::CubicOut))
+        .with(Tween::from_to(Property::Alpha, 1.0, 0.0, 0.4, Easing::QuadIn))
+}
+
+// Silhouettes of the character sprites in local coords
+// everything outside is transparent and neither drawn nor hit
+const PRINCESS_OUTLINE: &[(f32, f32)] = &[
+    (-0.25, 0.37), (0.21, 0.37), (0.29, 0.23), (0.43, 0.16), (0.59, 0.01), (0.70, -0.12),
+    (0.58, -0.26), (0.33, -0.40), (0.27, -0.55), (0.13, -0.64), (-0.13, -0.64), (-0.29, -0.55),
+    (-0.35, -0.40), (-0.62, -0.26), (-0.68, -0.12), (-0.60, 0.01), (-0.47, 0.16), (-0.31, 0.23)
+];
+
+const HORN_GIRL_OUTLINE: &[(f32, f32)] = &[
+    (-0.25, 0.27), (0.21, 0.27), (0.52, 0.19), (0.61, 0.05), (0.64, -0.09), (0.57, -0.26),
+    (0.47, -0.33), (0.31, -0.45), (0.27, -0.55), (0.12, -0.63), (-0.14, -0.63), (-0.25, -0.57),
+    (-0.33, -0.47), (-0.72, -0.45), (-0.88, -0.37), (-0.64, -0.30), (-0.60, -0.26), (-0.68, -0.09),
+    (-0.64, 0.05), (-0.55, 0.19)
+];
+
+// Components every figure has, it waits off the field until the spawner picks it
+fn spawn_shape(world: &mut World, scene: &mut Scene, shape: &Shape, kind: TargetKind) -> Entity {
+    let entity = world.spawn();
+
+    world.figures.insert(entity, Figure(scene.add_dynamic_shape(shape)));
+    world.transforms.insert(entity, shape.transform);
+    world.sprites.insert(entity, Sprite::new());
+    world.props.insert(entity, Props::default());
+    world.tweens.insert(entity, Tweener::new());
+    world.colliders.insert(entity, Collider);
+    world.targets.insert(entity, Target { kind });
+
+    entity
+}
+
+fn spawn_figure(world: &mut World, scene: &mut Scene, sprite: &str, outline: &[(f32, f32)], kind: TargetKind) -> Entity {
+    let outline: Vec<Point> = outline.iter().map(|&(x, y)| Point::new(x, y)).collect();
+
+    spawn_shape(world, scene, &Shape::polygon(&outline, sprite), kind)
+}
+
+// Special figures wait off the field until the spawn table picks them
+fn spawn_special(world: &mut World, scene: &mut Scene, special: Special) -> Entity {
+    let kind = if special.is_target() { TargetKind::Target } else { TargetKind::Bomb };
+
+    let entity = spawn_shape(world, scene, &Shape::circle(SPECIAL_RADIUS, 32, special.sprite()), kind);
+    world.specials.insert(entity, special);
+
+    entity
+}
+
+// Special figures are round, about as big as the characters
+const SPECIAL_RADIUS: f32 = 0.6;
+
+// Entries on the leaderboard screen
+const LEADERBOARD_SIZE: usize = 10;
+
+// Round time when accessible mode is enabled from the page url
+const ACCESSIBLE_ROUND_TIME: f64 = 3.0;
+
+// Seconds game over ignores Enter and taps,
+// so ones meant for the last figures do not start the next game
+const CONFIRM_DELAY: f64 = 0.5;
+
+// Loading screen does not wait for the music longer
+const LOADING_TIMEOUT: f64 = 3.0;
+
+const TITLE: &str = "Hit the princess";
+
+// Fresh game of `mode` with rounds of `round_time`
+fn new_state(mode: ModeKind, round_time: f64) -> GameState {
+    let mut state = GameState::new(mode);
+
+    state.set_round_time(round_time);
+    mode.rules().start(&mut state);
+
+    state
+}
+
+// How figures are hit, chosen by `?mode=slash` in the page url
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputMode {
+    // click or tap the figure
+    Tap,
+    // swipe across figures
+    Slash,
+}
+
+impl InputMode {
+    pub fn from_query(query: &str) -> InputMode {
+        match query_value(query, "mode") {
+            Some("slash") => InputMode::Slash,
+            _ => InputMode::Tap
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Game {
     window: web_sys::Window,
-    input_queue: Vec<Point>,
-    ui: Ui,
+    input_queue: Vec<PointerEvent>,
+    gestures: GestureRecognizer,
+    mode: InputMode,
+    // KeyboardEvent.key of pressed keys
+    key_queue: Vec<String>,
+    cursor: HoleCursor,
+    gamepad: GamepadInput,
+    // rounds are described to screen readers
+    accessible: bool,
+    announcer: Announcer,
+    // shared with the event bus
+    audio: Rc<RefCell<Audio>>,
+    persistence: Persistence<LocalStorageBackend>,
+    achievements: Rc<RefCell<Achievements>>,
+    bus: EventBus,
+    leaderboard: Box<dyn LeaderboardClient>,
+    // filled by the client when the answer comes
+    board: Rc<RefCell<LeaderboardView>>,
+    screens: ScreenMachine,
+    ui: Rc<RefCell<Ui>>,
     state: GameState,
+    rules: Box<dyn GameMode>,
+    spawns: SpawnTable,
+    spawner: Spawner,
+    // picked by the player, modes may change the one in state
+    round_time: f64,
     performance: web_sys::Performance,
     timestamp: f64,
-    scene: Scene
+    scene: Scene,
+    world: World
 }
 
 impl Game {
@@ -38,46 +214,160 @@ impl Game {
         canvas_ui.set_width(window.inner_width().unwrap().as_f64().unwrap() as u32);
         canvas_ui.set_height(window.inner_height().unwrap().as_f64().unwrap() as u32);
 
+        let persistence = Persistence::load(LocalStorageBackend::new());
+
+        if let Some(error) = persistence.error() {
+            log::write(&format!("Save is dropped: {:?}", error));
+        }
+
+        let query = window.location().search().unwrap_or_default();
+
+        let mode = InputMode::from_query(&query);
+        // url overrides saved settings
+        let settings = persistence.data().settings.clone();
+        let accessible = query_value(&query, "accessible") == Some("1") || settings.accessible;
+
+        let round_time = match query_value(&query, "round_time").and_then(|t| t.parse::<f64>().ok()).or(settings.round_time) {
+            Some(t) => t,
+            None if accessible => ACCESSIBLE_ROUND_TIME,
+            None => DEFAULT_ROUND_TIME
+        };
+
+        let game_mode = query_value(&query, "game").and_then(ModeKind::from_name);
+
+        // unfinished game goes on paused unless the url asks for another mode,
+        // otherwise the title screen offers the mode from the url or the last played one
+        let state = persistence.data().game.clone()
+            .filter(|state| game_mode.is_none_or(|mode| mode == state.mode()))
+            .unwrap_or_else(|| new_state(game_mode.unwrap_or(settings.mode), round_time));
+
         let mut ui = Ui::new(&canvas_ui);
-        ui.set_score(0);
-        ui.set_time(0.0);
+        ui.set_score(state.score());
+        ui.set_best(persistence.data().best_score);
+        let ui = Rc::new(RefCell::new(ui));
 
-        scene.add_dynamic_shape(&Shape::square(1));
-        scene.add_dynamic_shape(&Shape::square(3));
-        scene.add_dynamic_shape(&Shape::square(3));
+        let mut world = World::new();
 
-        scene.add_static_shape(&Shape::square(2));
+        spawn_figure(&mut world, &mut scene, "princess", PRINCESS_OUTLINE, TargetKind::Target);
+        spawn_figure(&mut world, &mut scene, "horn_girl", HORN_GIRL_OUTLINE, TargetKind::Decoy);
+        spawn_figure(&mut world, &mut scene, "horn_girl", HORN_GIRL_OUTLINE, TargetKind::Decoy);
+
+        for special in SPECIALS {
+            spawn_special(&mut world, &mut scene, special);
+        }
+
+        scene.add_static_shape(&Shape::square("brick"));
 
         scene.update_renders();
 
+        // weights are set by the mode, figures rise once the game is played
+        let mut spawner = Spawner::new(scene.hole_count(), vec![0; scene.dynamic_shapes_count()], |n| rand_in_range(0, n));
+        spawner.set_active(false);
+
         let performance: web_sys::Performance = window
             .performance()
             .expect("performance should be available");
 
-        Game {
+        // the first frame must not count time since the page load
+        let timestamp = performance.now();
+
+        let audio = Rc::new(RefCell::new(Audio::new()));
+        audio.borrow_mut().set_muted(settings.muted);
+
+        for (name, group) in [("volume", Group::Master), ("effects_volume", Group::Effects), ("music_volume", Group::Music)] {
+            if let Some(volume) = query_value(&query, name).and_then(|v| v.parse::<f32>().ok()) {
+                audio.borrow_mut().set_volume(group, volume);
+            }
+        }
+
+        // without backend only the local best score is shown
+        let leaderboard: Box<dyn LeaderboardClient> = match query_value(&query, "leaderboard") {
+            Some(url) => Box::new(HttpClient::new(url, &query)),
+            None => {
+                let best = persistence.data().best_score;
+                let player = (best > 0).then(|| Entry { rank: 1, name: String::from("You"), score: best });
+
+                Box::new(MockClient::new(Leaderboard { top: player.iter().cloned().collect(), player, friends: Vec::new() }))
+            }
+        };
+
+        let achievements = Rc::new(RefCell::new(Achievements::new(&persistence.data().unlocks)));
+
+        // game logic publishes events, the rest reacts to them
+        let mut bus = EventBus::new();
+        bus.subscribe(ui.clone());
+        bus.subscribe(audio.clone());
+        bus.subscribe(achievements.clone());
+
+        if query_value(&query, "haptics") != Some("0") {
+            bus.subscribe(Rc::new(RefCell::new(Haptics::new(window.navigator()))));
+        }
+
+        if query_value(&query, "log_events") == Some("1") {
+            bus.subscribe(Rc::new(RefCell::new(EventLog)));
+        }
+
+        let mut game = Game {
             window,
             input_queue: Vec::new(),
+            gestures: GestureRecognizer::new(),
+            mode,
+            key_queue: Vec::new(),
+            cursor: HoleCursor::new(GRID_COLUMNS as usize, GRID_ROWS as usize),
+            gamepad: GamepadInput::new(GamepadMapping::from_query(&query)),
+            accessible,
+            announcer: Announcer::new(&document, "announcer"),
+            audio,
+            achievements,
+            bus,
+            persistence,
+            leaderboard,
+            board: Rc::new(RefCell::new(LeaderboardView::Loading)),
+            screens: ScreenMachine::new(),
             ui,
-            state: GameState::new(),
+            rules: state.mode().rules(),
+            spawns: SpawnTable::from_query(&query),
+            spawner,
+            state,
+            round_time,
             performance,
-            timestamp: 0.0,
-            scene
-        }
+            timestamp,
+            scene,
+            world
+        };
+
+        game.update_spawn_weights();
+
+        game
     }
 
-    pub fn store_input(&mut self, input: Point) {
+    // Called from event handlers, so audio can be unlocked here
+    pub fn store_input(&mut self, input: PointerEvent) {
+        if let PointerEvent::Down(..) = input {
+            self.audio.borrow_mut().unlock();
+        }
+
         self.input_queue.push(input);
     }
 
+    pub fn store_key(&mut self, key: String) {
+        self.audio.borrow_mut().unlock();
+        self.key_queue.push(key);
+    }
+
     pub fn run(&mut self) {
         let dt = self.update_time();
+        self.audio.borrow_mut().update();
         self.handle_input();
-        self.scene.render(dt);
-        self.update_ui();
 
-        if self.scene.state() == State::Done && self.state.tick_timer(dt) {
-            self.update_scene();
-        }
+        self.screens.update(dt);
+        handler(self.screens.current()).update(self, dt);
+
+        tween_system(&mut self.world, dt);
+        render_system(&mut self.world, &mut self.scene);
+
+        self.scene.render(dt);
+        self.update_ui(dt);
     }
 
     pub fn window(&self) -> web_sys::Window {
@@ -89,19 +379,410 @@ impl Game {
     }
 
     fn handle_input(&mut self) {
-        if let Some(input) = self.input_queue.pop() {
-            if self.scene.state() == State::Done && self.scene.is_dynamic_hit(0, input) {
-                self.on_hit();
+        self.handle_keys();
+
+        let mut gestures: Vec<Gesture> = self.input_queue.drain(..)
+            .filter_map(|event| self.gestures.handle(event))
+            .collect();
+
+        gestures.extend(self.gestures.update(self.performance.now() / 1000.0));
+
+        for gesture in gestures {
+            if !self.can_hit() {
+                // tap on menus works like Enter, the rest of gestures is dropped
+                if let Gesture::Tap(_) = gesture {
+                    self.on_confirm();
+                    return;
+                }
+
+                continue;
+            }
+
+            match (self.mode, gesture) {
+                (InputMode::Tap, Gesture::Tap(point)) => {
+                    if let Some(entity) = hit_system(&self.world, &self.scene, point) {
+                        self.on_entity_hit(entity);
+                    }
+                },
+                (InputMode::Slash, Gesture::Stroke(segment)) => {
+                    for entity in slash_system(&self.world, &self.scene, &segment) {
+                        self.on_entity_hit(entity);
+                    }
+                },
+                // touch screens have no Esc, holding a finger still pauses
+                (_, Gesture::LongPress(_)) => {
+                    self.go(Screen::Paused);
+                    return;
+                },
+                // slashes hit by strokes while dragging, the whole swipe on release adds nothing
+                (_, Gesture::Swipe(_)) => {},
+                (InputMode::Tap, Gesture::Stroke(_)) | (InputMode::Slash, Gesture::Tap(_)) => {}
+            }
+        }
+    }
+
+    // Keys and gamepad share actions
+    fn handle_keys(&mut self) {
+        let mut actions: Vec<KeyAction> = self.key_queue.drain(..).filter_map(|key| key_action(&key)).collect();
+
+        if let Some(pad) = poll_gamepad(&self.window.navigator()) {
+            actions.extend(self.gamepad.update(pad));
+        }
+
+        for action in actions {
+            match action {
+                // numbers pick menu items outside of the game
+                KeyAction::Hit(number) if !self.can_hit() => self.on_pick(number),
+                KeyAction::Confirm if !self.can_hit() => self.on_confirm(),
+                KeyAction::Move(..) if !self.can_hit() => {},
+                KeyAction::Hit(number) => {
+                    self.cursor.select(number);
+                    self.show_cursor();
+                    self.hit_hole(number);
+                },
+                KeyAction::Move(dx, dy) => {
+                    let number = self.cursor.move_by(dx, dy);
+
+                    self.show_cursor();
+                    self.announce(&format!("Hole {}", number + 1));
+                },
+                KeyAction::Confirm => {
+                    if let Some(number) = self.cursor.selected() {
+                        self.hit_hole(number);
+                    }
+                },
+                KeyAction::ToggleAccessible => self.toggle_accessible(),
+                KeyAction::ToggleMute => self.toggle_mute(),
+                KeyAction::ToggleLeaderboard => self.toggle_overlay(Screen::Leaderboard),
+                KeyAction::ToggleSettings => self.toggle_overlay(Screen::Settings),
+                KeyAction::Pause => handler(self.screens.current()).back(self),
+                KeyAction::RoundTime(dt) => {
+                    self.state.set_round_time(self.state.round_time() + dt);
+                    self.round_time = self.state.round_time();
+
+                    self.persistence.data_mut().settings.round_time = Some(self.round_time);
+                    self.persistence.save();
+
+                    self.announce(&format!("Round time {:.1} seconds", self.state.round_time()));
+                }
             }
         }
     }
 
-    fn on_hit(&mut self) {
-        self.update_scene();
+    fn can_hit(&self) -> bool {
+        self.screens.current() == Screen::Playing
+    }
+
+    fn accepts_confirm(&self) -> bool {
+        self.screens.current() != Screen::GameOver || self.screens.time() >= CONFIRM_DELAY
+    }
+
+    // Enter or tap outside of the game
+    fn on_confirm(&mut self) {
+        if !self.accepts_confirm() {
+            return;
+        }
+
+        handler(self.screens.current()).confirm(self);
+    }
+
+    // Menu item by number
+    fn on_pick(&mut self, number: usize) {
+        if !self.accepts_confirm() {
+            return;
+        }
+
+        handler(self.screens.current()).pick(self, number);
+    }
+
+    fn toggle_accessible(&mut self) {
+        self.accessible = !self.accessible;
+
+        self.persistence.data_mut().settings.accessible = self.accessible;
+        self.persistence.save();
+
+        let text = if self.accessible { "Accessible mode on" } else { "Accessible mode off" };
+        self.announcer.announce(text);
+    }
+
+    fn toggle_mute(&mut self) {
+        let muted = !self.audio.borrow().muted();
+        self.audio.borrow_mut().set_muted(muted);
+
+        self.persistence.data_mut().settings.muted = muted;
+        self.persistence.save();
+
+        self.announce(if muted { "Sound off" } else { "Sound on" });
+    }
+
+    // Overlay opened during the game pauses it
+    fn toggle_overlay(&mut self, overlay: Screen) {
+        if self.screens.current() == overlay {
+            self.close_overlay();
+            return;
+        }
+
+        if self.screens.current() == Screen::Playing {
+            self.go(Screen::Paused);
+        }
+
+        self.go(overlay);
+    }
+
+    fn close_overlay(&mut self) {
+        if let Some(back) = self.screens.back() {
+            self.go(back);
+        }
+    }
 
-        self.state.add_score();
+    // Screens without such transition are left as they are
+    fn go(&mut self, screen: Screen) {
+        let from = self.screens.current();
 
-        self.ui.set_score(self.state.score());
+        if self.screens.go(screen) {
+            handler(from).exit(self, screen);
+            handler(screen).enter(self, from);
+        }
+    }
+
+    // Unfinished game from the save waits paused
+    fn finish_loading(&mut self) {
+        if self.persistence.data().game.as_ref() == Some(&self.state) {
+            self.go(Screen::Paused);
+            self.emit(GameEvent::GameResumed { mode: self.state.mode(), score: self.state.score() });
+        } else {
+            self.go(Screen::Title);
+        }
+    }
+
+    fn start_game(&mut self, mode: ModeKind) {
+        self.state = new_state(mode, self.round_time);
+        self.rules = mode.rules();
+
+        self.update_spawn_weights();
+
+        let data = self.persistence.data_mut();
+        data.total_games += 1;
+        data.settings.mode = mode;
+        data.game = Some(self.state.clone());
+        self.persistence.save();
+
+        self.go(Screen::Playing);
+        self.emit(GameEvent::GameStarted { mode });
+    }
+
+    // Clocks are useless without the game clock
+    fn update_spawn_weights(&mut self) {
+        let weights: Vec<(usize, u32)> = self.world.targets.iter()
+            .filter_map(|(entity, target)| {
+                let special = self.world.specials.get(entity).copied();
+                let weight = match special {
+                    Some(Special::Clock) if !self.rules.has_clock() => 0,
+                    _ => self.spawns.weight(target.kind, special)
+                };
+
+                Some((self.world.figures.get(entity)?.0, weight))
+            })
+            .collect();
+
+        for (figure, weight) in weights {
+            self.spawner.set_weight(figure, weight);
+        }
+    }
+
+    fn check_over(&mut self) {
+        if self.rules.is_over(&self.state) {
+            self.go(Screen::GameOver);
+        }
+    }
+
+    // Subscribers get the event first, then the game handles it and its follow-ups
+    fn emit(&mut self, event: GameEvent) {
+        for event in self.bus.publish(event) {
+            self.on_event(&event);
+        }
+    }
+
+    // Unlocks are saved, the rest is told to screen readers
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::GameStarted { mode } | GameEvent::GameResumed { mode, .. } => self.announce(&format!("{} game", mode.title())),
+            &GameEvent::TargetSpawned { hole, kind, .. } => self.announce_spawn(hole, kind),
+            GameEvent::TargetHit { hit, score, .. } => {
+                let perfect = if hit.is_perfect() { "Perfect hit" } else { "Hit" };
+                self.announce(&format!("{}, {} points, combo {}, score {}", perfect, hit.total(), hit.combo, score));
+            },
+            GameEvent::BombHit => self.announce("Bomb"),
+            GameEvent::TargetMissed => self.announce("Missed"),
+            GameEvent::AchievementUnlocked { achievement } => {
+                self.persistence.unlock(&achievement.unlock_name());
+                self.announce(&format!("Achievement unlocked: {}, {}", achievement.title, achievement.description));
+            },
+            GameEvent::GameOver { score, .. } => {
+                self.announce(&format!("Game over, score {}. Enter plays again, 1 to {} pick a mode", score, MODES.len()));
+            },
+            GameEvent::DecoyHit | GameEvent::NewBest { .. } => {}
+        }
+    }
+
+    fn fetch_leaderboard(&mut self) {
+        *self.board.borrow_mut() = LeaderboardView::Loading;
+
+        let board = self.board.clone();
+
+        self.leaderboard.fetch(LEADERBOARD_SIZE, Box::new(move |result| {
+            *board.borrow_mut() = LeaderboardView::from(result);
+        }));
+    }
+
+    // Keyboard hit ignores sprite shape, whole hole counts
+    fn hit_hole(&mut self, number: usize) {
+        if !self.can_hit() {
+            return;
+        }
+
+        let Some(idx) = self.scene.hole_by_number(number).and_then(|hole| self.scene.figure_in_hole(hole)) else {
+            return;
+        };
+
+        if let Some(entity) = self.entity_of(idx) {
+            self.on_entity_hit(entity);
+        }
+    }
+
+    fn show_cursor(&mut self) {
+        let hole = self.cursor.selected().and_then(|number| self.scene.hole_by_number(number));
+
+        self.scene.set_cursor(hole.map(|hole| self.scene.hole_position(hole)));
+    }
+
+    fn announce(&self, text: &str) {
+        if self.accessible {
+            self.announcer.announce(text);
+        }
+    }
+
+    fn entity_of(&self, idx: usize) -> Option<Entity> {
+        self.world.figures.iter().find(|(_, figure)| figure.0 == idx).map(|(entity, _)| entity)
+    }
+
+    fn tween(&mut self, entity: Entity, sequence: Sequence) {
+        if let Some(tweens) = self.world.tweens.get_mut(entity) {
+            tweens.add(sequence);
+        }
+    }
+
+    fn play(&mut self, entity: Entity, clip: Clip) {
+        if let Some(sprite) = self.world.sprites.get_mut(entity) {
+            sprite.play(clip);
+        }
+    }
+
+    // Figure rises again without leftovers of the previous time
+    fn reset_entity(&mut self, entity: Entity) {
+        self.play(entity, Clip::Idle);
+
+        if let Some(props) = self.world.props.get_mut(entity) {
+            *props = Props::default();
+        }
+
+        if let Some(tweens) = self.world.tweens.get_mut(entity) {
+            tweens.clear();
+        }
+    }
+
+    // Tells where targets and bombs are, decoys are not worth it
+    fn announce_spawn(&self, hole: usize, kind: TargetKind) {
+        let number = self.scene.hole_number(hole) + 1;
+
+        match kind {
+            TargetKind::Target => self.announce(&format!("Target in hole {} of {}", number, self.scene.hole_count())),
+            TargetKind::Bomb => self.announce(&format!("Bomb in hole {}", number)),
+            _ => {}
+        }
+    }
+
+    // Only figures which are rising or up can be hit
+    fn on_entity_hit(&mut self, entity: Entity) {
+        let (Some(&Target { kind }), Some(&Figure(idx))) = (self.world.targets.get(entity), self.world.figures.get(entity)) else {
+            return;
+        };
+
+        let Some(hole) = self.scene.hole_of_figure(idx).filter(|&hole| self.spawner.is_hittable(hole)) else {
+            return;
+        };
+
+        self.spawner.knock(hole);
+        self.play(entity, Clip::Hit);
+
+        match kind {
+            TargetKind::Target => self.on_hit(entity, idx, hole),
+            TargetKind::Decoy => {
+                self.tween(entity, shake());
+                self.state.break_combo();
+                self.rules.on_decoy_hit(&mut self.state);
+                self.save_game();
+
+                self.emit(GameEvent::DecoyHit);
+                self.check_over();
+            },
+            TargetKind::Bomb => self.on_bomb(idx)
+        }
+    }
+
+    // Counts as a miss
+    fn on_bomb(&mut self, idx: usize) {
+        self.scene.hit_effect(idx, explosion());
+
+        self.state.break_combo();
+        self.rules.on_miss(&mut self.state);
+        self.save_game();
+
+        self.emit(GameEvent::BombHit);
+        self.check_over();
+    }
+
+    fn on_hit(&mut self, entity: Entity, idx: usize, hole: usize) {
+        self.tween(entity, shake());
+        self.scene.hit_effect(idx, hit_effect());
+
+        let hit = self.state.add_hit(self.spawner.reaction_time(hole), special_bonus(&self.world, entity));
+        self.rules.on_hit(&mut self.state);
+
+        let mut lines = hit.lines();
+        lines.extend(special_system(&self.world, entity, &mut self.state));
+
+        let special = self.world.specials.get(entity).copied();
+
+        self.scene.score_popups(idx, &lines, score_popup);
+
+        let score = self.state.score();
+        self.persistence.data_mut().game = Some(self.state.clone());
+
+        self.emit(GameEvent::TargetHit { hit, special, score });
+
+        if self.persistence.submit_score(score) {
+            self.leaderboard.submit(score);
+            self.emit(GameEvent::NewBest { score });
+        } else {
+            self.persistence.save();
+        }
+
+        self.check_over();
+    }
+
+    fn on_miss(&mut self) {
+        self.state.break_combo();
+        self.rules.on_miss(&mut self.state);
+        self.save_game();
+
+        self.emit(GameEvent::TargetMissed);
+        self.check_over();
+    }
+
+    fn save_game(&mut self) {
+        self.persistence.data_mut().game = Some(self.state.clone());
+        self.persistence.save();
     }
 
     fn update_time(&mut self) -> f64 {
@@ -112,20 +793,361 @@ impl Game {
         dt
     }
 
-    fn update_scene(&mut self) {
-        self.scene.permutate_transforms();
-        self.scene.reset();
-        self.state.start_timer();
-    }
+    fn update_holes(&mut self, dt: f64) {
+        for event in self.spawner.update(dt, self.state.round_time()) {
+            match event {
+                SpawnEvent::Spawned { hole, figure } => {
+                    self.scene.place_figure(figure, Some(hole));
 
-    fn update_ui(&mut self) {
-        match self.scene.state() {
-            State::Initial => {
-                self.ui.clear_timer();
-            },
-            State::Done => {
-                self.ui.set_time(self.state.time());
+                    if let Some(entity) = self.entity_of(figure) {
+                        self.reset_entity(entity);
+                        self.tween(entity, pop_up());
+
+                        let kind = self.world.targets.get(entity).map_or(TargetKind::Decoy, |target| target.kind);
+                        let special = self.world.specials.get(entity).copied();
+
+                        self.emit(GameEvent::TargetSpawned { hole, kind, special });
+                    }
+                },
+                SpawnEvent::Escaped { figure, .. } => {
+                    let Some(entity) = self.entity_of(figure) else {
+                        continue;
+                    };
+
+                    self.play(entity, Clip::Escape);
+
+                    if self.world.targets.get(entity).is_some_and(|target| target.kind == TargetKind::Target) {
+                        self.on_miss();
+                    }
+                },
+                SpawnEvent::Hidden { figure, .. } => self.scene.place_figure(figure, None)
             }
         }
+
+        for hole in 0..self.scene.hole_count() {
+            if let Some(figure) = self.spawner.figure(hole) {
+                self.scene.set_rise(figure, self.spawner.rise(hole));
+            }
+        }
+    }
+
+    fn update_ui(&mut self, dt: f64) {
+        let mut ui = self.ui.borrow_mut();
+
+        ui.set_popups(&self.scene.labels());
+        ui.update_toasts(dt);
+
+        match self.state.combo() {
+            combo if combo > 1 => ui.set_status(&format!("{}  Combo: {}", self.rules.status(&self.state), combo)),
+            _ => ui.set_status(&self.rules.status(&self.state))
+        }
+
+        handler(self.screens.current()).render(self, &mut ui);
+    }
+
+    fn mode_lines(&self) -> Vec<String> {
+        MODES.iter().enumerate().map(|(i, mode)| format!("{}. {}", i + 1, mode.title())).collect()
+    }
+}
+
+// Everything one screen does: on the way in and out, every frame, on the panel and on input
+trait ScreenHandler {
+    fn enter(&self, _game: &mut Game, _from: Screen) {}
+
+    fn exit(&self, _game: &mut Game, _to: Screen) {}
+
+    fn update(&self, _game: &mut Game, _dt: f64) {}
+
+    fn render(&self, game: &Game, ui: &mut Ui);
+
+    // Enter or tap
+    fn confirm(&self, _game: &mut Game) {}
+
+    // menu item by number
+    fn pick(&self, _game: &mut Game, _number: usize) {}
+
+    // Esc
+    fn back(&self, _game: &mut Game) {}
+}
+
+fn handler(screen: Screen) -> &'static dyn ScreenHandler {
+    match screen {
+        Screen::Loading => &LoadingScreen,
+        Screen::Title => &TitleScreen,
+        Screen::Playing => &PlayingScreen,
+        Screen::Paused => &PausedScreen,
+        Screen::GameOver => &GameOverScreen,
+        Screen::Leaderboard => &LeaderboardScreen,
+        Screen::Settings => &SettingsScreen
     }
-}
\ No newline at end of file
+}
+
+struct LoadingScreen;
+
+impl ScreenHandler for LoadingScreen {
+    fn update(&self, game: &mut Game, _dt: f64) {
+        if game.audio.borrow().is_loaded() || game.screens.time() >= LOADING_TIMEOUT {
+            game.finish_loading();
+        }
+    }
+
+    fn render(&self, _game: &Game, ui: &mut Ui) {
+        ui.draw_panel(&[String::from("Loading...")]);
+    }
+}
+
+struct TitleScreen;
+
+impl ScreenHandler for TitleScreen {
+    fn enter(&self, game: &mut Game, _from: Screen) {
+        // field stays empty on menus
+        game.spawner.set_active(false);
+        game.spawner.knock_all();
+
+        game.announce(&format!("{}. Enter plays {}, 1 to {} pick a mode", TITLE, game.state.mode().title(), MODES.len()));
+    }
+
+    // figures left from the game sink
+    fn update(&self, game: &mut Game, dt: f64) {
+        game.update_holes(dt);
+    }
+
+    fn render(&self, game: &Game, ui: &mut Ui) {
+        let mut lines = vec![
+            String::from(TITLE),
+            String::new(),
+            format!("Best: {}", game.persistence.data().best_score),
+            format!("Achievements: {}/{}", game.achievements.borrow().unlocked_count(), ACHIEVEMENTS.len()),
+            String::new()
+        ];
+
+        lines.extend(game.mode_lines());
+
+        lines.push(String::new());
+        lines.push(format!("Tap or Enter to play {}", game.state.mode().title()));
+        lines.push(String::from("L leaderboard, O settings"));
+
+        ui.draw_panel(&lines);
+    }
+
+    fn confirm(&self, game: &mut Game) {
+        game.start_game(game.state.mode());
+    }
+
+    fn pick(&self, game: &mut Game, number: usize) {
+        if let Some(&mode) = MODES.get(number) {
+            game.start_game(mode);
+        }
+    }
+}
+
+struct PlayingScreen;
+
+impl ScreenHandler for PlayingScreen {
+    fn enter(&self, game: &mut Game, from: Screen) {
+        game.spawner.set_active(true);
+        game.show_cursor();
+
+        if from == Screen::Paused {
+            game.announce("Go on");
+        }
+    }
+
+    fn exit(&self, game: &mut Game, _to: Screen) {
+        game.scene.set_cursor(None);
+    }
+
+    fn update(&self, game: &mut Game, dt: f64) {
+        game.state.update_effects(dt);
+        game.rules.update(&mut game.state, dt);
+        game.check_over();
+
+        // freeze slows figures down, animations keep their pace
+        game.update_holes(dt*game.state.time_scale());
+    }
+
+    fn render(&self, _game: &Game, ui: &mut Ui) {
+        ui.clear_panel();
+    }
+
+    fn back(&self, game: &mut Game) {
+        game.go(Screen::Paused);
+    }
+}
+
+// Field is frozen under the pause and the overlays opened from it
+struct PausedScreen;
+
+impl ScreenHandler for PausedScreen {
+    fn enter(&self, game: &mut Game, from: Screen) {
+        if from == Screen::Playing {
+            game.save_game();
+        }
+
+        if !from.is_overlay() {
+            game.announce("Paused. Enter goes on, 2 quits");
+        }
+    }
+
+    fn exit(&self, game: &mut Game, to: Screen) {
+        // quit game is not resumed
+        if to == Screen::Title {
+            game.persistence.data_mut().game = None;
+            game.persistence.save();
+        }
+    }
+
+    fn render(&self, game: &Game, ui: &mut Ui) {
+        ui.draw_panel(&[
+            String::from("Paused"),
+            String::from(game.state.mode().title()),
+            String::new(),
+            format!("Score: {}", game.state.score()),
+            String::new(),
+            String::from("1. Go on"),
+            String::from("2. Quit"),
+            String::new(),
+            String::from("Tap or Enter to go on"),
+            String::from("L leaderboard, O settings")
+        ]);
+    }
+
+    fn confirm(&self, game: &mut Game) {
+        game.go(Screen::Playing);
+    }
+
+    fn pick(&self, game: &mut Game, number: usize) {
+        match number {
+            0 => game.go(Screen::Playing),
+            1 => game.go(Screen::Title),
+            _ => {}
+        }
+    }
+
+    fn back(&self, game: &mut Game) {
+        game.go(Screen::Playing);
+    }
+}
+
+struct GameOverScreen;
+
+impl ScreenHandler for GameOverScreen {
+    // the game is over once, not on every return from overlays
+    fn enter(&self, game: &mut Game, from: Screen) {
+        if from.is_overlay() {
+            return;
+        }
+
+        game.persistence.data_mut().game = None;
+        game.persistence.save();
+
+        game.spawner.set_active(false);
+        game.spawner.knock_all();
+
+        game.emit(GameEvent::GameOver { mode: game.state.mode(), score: game.state.score() });
+    }
+
+    fn update(&self, game: &mut Game, dt: f64) {
+        game.update_holes(dt);
+    }
+
+    fn render(&self, game: &Game, ui: &mut Ui) {
+        let mut lines = vec![
+            String::from("Game over"),
+            String::from(game.state.mode().title()),
+            format!("Score: {}", game.state.score()),
+            format!("Best combo: {}", game.state.best_combo()),
+            format!("Best: {}", game.persistence.data().best_score),
+            format!("Achievements: {}/{}", game.achievements.borrow().unlocked_count(), ACHIEVEMENTS.len()),
+            String::new()
+        ];
+
+        lines.extend(game.mode_lines());
+
+        lines.push(String::new());
+        lines.push(String::from("Tap or Enter to play again"));
+        lines.push(String::from("Esc to title"));
+
+        ui.draw_panel(&lines);
+    }
+
+    fn confirm(&self, game: &mut Game) {
+        game.start_game(game.state.mode());
+    }
+
+    fn pick(&self, game: &mut Game, number: usize) {
+        if let Some(&mode) = MODES.get(number) {
+            game.start_game(mode);
+        }
+    }
+
+    fn back(&self, game: &mut Game) {
+        game.go(Screen::Title);
+    }
+}
+
+struct LeaderboardScreen;
+
+impl ScreenHandler for LeaderboardScreen {
+    fn enter(&self, game: &mut Game, _from: Screen) {
+        game.fetch_leaderboard();
+        game.announce("Leaderboard");
+    }
+
+    fn render(&self, game: &Game, ui: &mut Ui) {
+        ui.draw_leaderboard(&game.board.borrow());
+    }
+
+    fn confirm(&self, game: &mut Game) {
+        game.close_overlay();
+    }
+
+    fn back(&self, game: &mut Game) {
+        game.close_overlay();
+    }
+}
+
+struct SettingsScreen;
+
+impl SettingsScreen {
+    fn lines(game: &Game) -> Vec<String> {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+
+        vec![
+            String::from("Settings"),
+            String::new(),
+            format!("1. Sound: {}", on_off(!game.audio.borrow().muted())),
+            format!("2. Accessible mode: {}", on_off(game.accessible)),
+            format!("Round time: {:.1} s, + and - change it", game.round_time),
+            String::new(),
+            String::from("Tap or O to close")
+        ]
+    }
+}
+
+impl ScreenHandler for SettingsScreen {
+    fn enter(&self, game: &mut Game, _from: Screen) {
+        let lines: Vec<String> = SettingsScreen::lines(game).into_iter().skip(1).filter(|line| !line.is_empty()).collect();
+        game.announce(&lines.join(". "));
+    }
+
+    fn render(&self, game: &Game, ui: &mut Ui) {
+        ui.draw_panel(&SettingsScreen::lines(game));
+    }
+
+    fn confirm(&self, game: &mut Game) {
+        game.close_overlay();
+    }
+
+    fn pick(&self, game: &mut Game, number: usize) {
+        match number {
+            0 => game.toggle_mute(),
+            1 => game.toggle_accessible(),
+            _ => {}
+        }
+    }
+
+    fn back(&self, game: &mut Game) {
+        game.close_overlay();
+    }
+}
@@ -0,0 +1,284 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::game_state::GameState;
+
+use serde::{Deserialize, Serialize};
+
+const CLASSIC_LIVES: u32 = 3;
+// Time attack length in seconds
+const TIME_ATTACK_TIME: f64 = 60.0;
+// Endless round time is multiplied by it after every hit
+const ENDLESS_SPEEDUP: f64 = 0.93;
+
+// Saved with the unfinished game, names match `?game=` values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeKind {
+    #[default]
+    Classic,
+    TimeAttack,
+    Endless,
+    Zen,
+}
+
+// In menu order
+pub const MODES: [ModeKind; 4] = [ModeKind::Classic, ModeKind::TimeAttack, ModeKind::Endless, ModeKind::Zen];
+
+impl ModeKind {
+    // e.g. `?game=time_attack`
+    pub fn from_name(name: &str) -> Option<ModeKind> {
+        MODES.iter().copied().find(|mode| mode.id() == name)
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            ModeKind::Classic => "classic",
+            ModeKind::TimeAttack => "time_attack",
+            ModeKind::Endless => "endless",
+            ModeKind::Zen => "zen"
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            ModeKind::Classic => "Classic",
+            ModeKind::TimeAttack => "Time attack",
+            ModeKind::Endless => "Endless",
+            ModeKind::Zen => "Zen"
+        }
+    }
+
+    pub fn rules(self) -> Box<dyn GameMode> {
+        match self {
+            ModeKind::Classic => Box::new(Classic),
+            ModeKind::TimeAttack => Box::new(TimeAttack),
+            ModeKind::Endless => Box::new(Endless),
+            ModeKind::Zen => Box::new(Zen)
+        }
+    }
+}
+
+// Rules of a mode, everything they track lives in GameState so it is saved
+pub trait GameMode: std::fmt::Debug {
+    // Prepares a fresh state
+    fn start(&self, _state: &mut GameState) {}
+
+    fn on_hit(&self, _state: &mut GameState) {}
+
+    // target was not hit in time
+    fn on_miss(&self, _state: &mut GameState) {}
+
+    fn on_decoy_hit(&self, _state: &mut GameState) {}
+
+    fn update(&self, _state: &mut GameState, _dt: f64) {}
+
+    // clock figures are spawned only for modes with the game clock
+    fn has_clock(&self) -> bool {
+        false
+    }
+
+    fn is_over(&self, state: &GameState) -> bool;
+
+    // shown next to the score
+    fn status(&self, state: &GameState) -> String;
+}
+
+// Misses and decoys take lives
+#[derive(Debug)]
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn start(&self, state: &mut GameState) {
+        state.set_lives(CLASSIC_LIVES);
+    }
+
+    fn on_miss(&self, state: &mut GameState) {
+        state.lose_life();
+    }
+
+    fn on_decoy_hit(&self, state: &mut GameState) {
+        state.lose_life();
+    }
+
+    fn is_over(&self, state: &GameState) -> bool {
+        state.lives() == 0
+    }
+
+    fn status(&self, state: &GameState) -> String {
+        format!("Lives: {}", state.lives())
+    }
+}
+
+// As many hits as possible in a minute, misses cost nothing
+#[derive(Debug)]
+pub struct TimeAttack;
+
+impl GameMode for TimeAttack {
+    fn update(&self, state: &mut GameState, dt: f64) {
+        state.add_elapsed(dt);
+    }
+
+    fn has_clock(&self) -> bool {
+        true
+    }
+
+    fn is_over(&self, state: &GameState) -> bool {
+        state.elapsed() >= TIME_ATTACK_TIME
+    }
+
+    fn status(&self, state: &GameState) -> String {
+        format!("Left: {:.0}", (TIME_ATTACK_TIME - state.elapsed()).max(0.0).ceil())
+    }
+}
+
+// Figures stay up shorter with every hit, the first miss ends the game
+#[derive(Debug)]
+pub struct Endless;
+
+impl GameMode for Endless {
+    fn start(&self, state: &mut GameState) {
+        state.set_lives(1);
+    }
+
+    fn on_hit(&self, state: &mut GameState) {
+        state.set_round_time(state.round_time()*ENDLESS_SPEEDUP);
+    }
+
+    fn on_miss(&self, state: &mut GameState) {
+        state.lose_life();
+    }
+
+    fn is_over(&self, state: &GameState) -> bool {
+        state.lives() == 0
+    }
+
+    fn status(&self, state: &GameState) -> String {
+        format!("Round: {:.2}", state.round_time())
+    }
+}
+
+// Never ends
+#[derive(Debug)]
+pub struct Zen;
+
+impl GameMode for Zen {
+    fn is_over(&self, _state: &GameState) -> bool {
+        false
+    }
+
+    fn status(&self, _state: &GameState) -> String {
+        String::from("Zen")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::MIN_ROUND_TIME;
+
+    fn start(mode: ModeKind) -> (Box<dyn GameMode>, GameState) {
+        let rules = mode.rules();
+        let mut state = GameState::new(mode);
+        rules.start(&mut state);
+
+        (rules, state)
+    }
+
+    #[test]
+    fn classic_ends_when_lives_are_gone() {
+        let (rules, mut state) = start(ModeKind::Classic);
+        assert_eq!(state.lives(), CLASSIC_LIVES);
+
+        rules.on_hit(&mut state);
+        rules.update(&mut state, 1000.0);
+        assert_eq!(state.lives(), CLASSIC_LIVES);
+
+        rules.on_miss(&mut state);
+        rules.on_decoy_hit(&mut state);
+        assert_eq!(state.lives(), CLASSIC_LIVES - 2);
+        assert!(!rules.is_over(&state));
+        assert_eq!(rules.status(&state), "Lives: 1");
+
+        rules.on_miss(&mut state);
+        assert!(rules.is_over(&state));
+
+        // never below zero
+        rules.on_miss(&mut state);
+        assert_eq!(state.lives(), 0);
+    }
+
+    #[test]
+    fn time_attack_runs_on_the_clock() {
+        let (rules, mut state) = start(ModeKind::TimeAttack);
+        assert!(rules.has_clock());
+
+        // misses and decoys cost nothing
+        rules.on_miss(&mut state);
+        rules.on_decoy_hit(&mut state);
+        assert!(!rules.is_over(&state));
+
+        rules.update(&mut state, TIME_ATTACK_TIME - 0.5);
+        assert!(!rules.is_over(&state));
+        assert_eq!(rules.status(&state), "Left: 1");
+
+        // clock figures give time back
+        state.add_time(5.0);
+        rules.update(&mut state, 5.0);
+        assert!(!rules.is_over(&state));
+
+        rules.update(&mut state, 0.5);
+        assert!(rules.is_over(&state));
+        assert_eq!(rules.status(&state), "Left: 0");
+    }
+
+    #[test]
+    fn endless_speeds_up_to_the_limit() {
+        let (rules, mut state) = start(ModeKind::Endless);
+        let start_time = state.round_time();
+
+        rules.on_hit(&mut state);
+        assert!((state.round_time() - start_time*ENDLESS_SPEEDUP).abs() < 1e-9);
+
+        rules.on_hit(&mut state);
+        assert!((state.round_time() - start_time*ENDLESS_SPEEDUP*ENDLESS_SPEEDUP).abs() < 1e-9);
+
+        // clamped at the shortest round time
+        for _ in 0..100 {
+            rules.on_hit(&mut state);
+        }
+
+        assert_eq!(state.round_time(), MIN_ROUND_TIME);
+
+        // decoys do not matter, the first miss ends the game
+        rules.on_decoy_hit(&mut state);
+        assert!(!rules.is_over(&state));
+        rules.on_miss(&mut state);
+        assert!(rules.is_over(&state));
+    }
+
+    #[test]
+    fn zen_never_ends() {
+        let (rules, mut state) = start(ModeKind::Zen);
+        let round_time = state.round_time();
+
+        for _ in 0..10 {
+            rules.on_miss(&mut state);
+            rules.on_decoy_hit(&mut state);
+            rules.on_hit(&mut state);
+            rules.update(&mut state, 1000.0);
+        }
+
+        assert!(!rules.is_over(&state));
+        assert!(!rules.has_clock());
+        assert_eq!(state.round_time(), round_time);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mode in MODES {
+            assert_eq!(ModeKind::from_name(mode.id()), Some(mode));
+        }
+
+        assert_eq!(ModeKind::from_name("arcade"), None);
+    }
+}
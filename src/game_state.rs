@@ -1,48 +1,128 @@
-const DEFAULT_ROUND_TIME: f64 = 1.0; // 1 sec
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::game_mode::ModeKind;
+use crate::scoring::HitScore;
+use crate::special::FREEZE_SCALE;
+
+pub const DEFAULT_ROUND_TIME: f64 = 1.0; // 1 sec
+pub const MIN_ROUND_TIME: f64 = 0.5;
+const MAX_ROUND_TIME: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GameState {
+    mode: ModeKind,
     score: u64,
-    timer: f64,
+    // how long figures stay up
+    round_time: f64,
+    // modes that do not count lives keep it 0
+    lives: u32,
+    // seconds since the game started
+    elapsed: f64,
+    // hits in a row
+    combo: u32,
+    best_combo: u32,
+    // seconds of freeze left, it is not worth saving
+    #[serde(skip)]
+    freeze: f64,
 }
 
 impl GameState {
-    pub fn new() -> GameState {
+    pub fn new(mode: ModeKind) -> GameState {
         GameState {
+            mode,
             score: 0,
-            timer: DEFAULT_ROUND_TIME
+            round_time: DEFAULT_ROUND_TIME,
+            lives: 0,
+            elapsed: 0.0,
+            combo: 0,
+            best_combo: 0,
+            freeze: 0.0
         }
     }
 
-    pub fn add_score(&mut self) {
-        self.score += 1;
+    pub fn mode(&self) -> ModeKind {
+        self.mode
+    }
+
+    // Scores the hit by how fast it came after the figure started rising
+    pub fn add_hit(&mut self, reaction_time: f64, bonus: u64) -> HitScore {
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+
+        let hit = HitScore::new(self.combo, reaction_time, self.round_time, bonus);
+
+        self.score += hit.total();
+
+        hit
+    }
+
+    // Misses and decoys start the combo over
+    pub fn break_combo(&mut self) {
+        self.combo = 0;
+    }
+
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    pub fn best_combo(&self) -> u32 {
+        self.best_combo
     }
 
     pub fn score(&self) -> u64 {
         self.score
     }
 
-    pub fn start_timer(&mut self) {
-        self.timer = DEFAULT_ROUND_TIME;
+    // Applied to figures which rise after it
+    pub fn set_round_time(&mut self, t: f64) {
+        self.round_time = t.clamp(MIN_ROUND_TIME, MAX_ROUND_TIME);
     }
 
-    pub fn tick_timer(&mut self, dt: f64) -> bool {
-        self.timer -= dt;
+    pub fn round_time(&self) -> f64 {
+        self.round_time
+    }
 
-        let result = self.timer <= 0.0;
+    pub fn set_lives(&mut self, lives: u32) {
+        self.lives = lives;
+    }
+
+    pub fn lose_life(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+    }
 
-        self.timer = self.timer.clamp(0.0, DEFAULT_ROUND_TIME);
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
 
-        result
+    pub fn add_elapsed(&mut self, dt: f64) {
+        self.elapsed += dt;
     }
 
-    pub fn time(&self) -> f64 {
-        self.timer
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // Game clock goes back, e.g. by a clock figure
+    pub fn add_time(&mut self, t: f64) {
+        self.elapsed -= t;
+    }
+
+    pub fn freeze(&mut self, t: f64) {
+        self.freeze = self.freeze.max(t);
+    }
+
+    pub fn update_effects(&mut self, dt: f64) {
+        self.freeze = (self.freeze - dt).max(0.0);
+    }
+
+    // Figures go this much slower
+    pub fn time_scale(&self) -> f64 {
+        if self.freeze > 0.0 { FREEZE_SCALE } else { 1.0 }
     }
 }
 
 impl Default for GameState {
     fn default() -> Self {
-        GameState::new()
+        GameState::new(ModeKind::default())
     }
-}
\ No newline at end of file
+}
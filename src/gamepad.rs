@@ -0,0 +1,249 @@
+use wasm_bindgen::prelude::*;
+
+#[allow(unused_imports)]
+use crate::log;
+use crate::keyboard::KeyAction;
+
+// Stick deflection smaller than this is ignored
+const DEAD_ZONE: f32 = 0.5;
+
+// Button and axis indices, defaults follow the standard gamepad layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GamepadMapping {
+    pub hit: usize,
+    pub pause: usize,
+    pub up: usize,
+    pub down: usize,
+    pub left: usize,
+    pub right: usize,
+    pub stick_x: usize,
+    pub stick_y: usize
+}
+
+impl GamepadMapping {
+    // Overrides from `pad_<name>=<index>` pairs of page url query
+    pub fn from_query(query: &str) -> GamepadMapping {
+        let mut mapping = GamepadMapping::default();
+
+        for (name, value) in query.trim_start_matches('?').split('&').filter_map(|pair| pair.split_once('=')) {
+            let Ok(index) = value.parse::<usize>() else {
+                continue;
+            };
+
+            match name {
+                "pad_hit" => mapping.hit = index,
+                "pad_pause" => mapping.pause = index,
+                "pad_up" => mapping.up = index,
+                "pad_down" => mapping.down = index,
+                "pad_left" => mapping.left = index,
+                "pad_right" => mapping.right = index,
+                "pad_stick_x" => mapping.stick_x = index,
+                "pad_stick_y" => mapping.stick_y = index,
+                _ => {}
+            }
+        }
+
+        mapping
+    }
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        GamepadMapping {
+            hit: 0,
+            pause: 9,
+            up: 12,
+            down: 13,
+            left: 14,
+            right: 15,
+            stick_x: 0,
+            stick_y: 1
+        }
+    }
+}
+
+// State of one gamepad at the moment of polling
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PadState {
+    pub buttons: Vec<bool>,
+    pub axes: Vec<f32>
+}
+
+impl PadState {
+    fn pressed(&self, button: usize) -> bool {
+        self.buttons.get(button).copied().unwrap_or(false)
+    }
+
+    fn axis(&self, axis: usize) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+}
+
+// Turns polled states into the same actions as keys
+// buttons and stick fire once when pressed or tilted
+#[derive(Debug, Default)]
+pub struct GamepadInput {
+    mapping: GamepadMapping,
+    previous: PadState,
+    previous_stick: (i32, i32)
+}
+
+impl GamepadInput {
+    pub fn new(mapping: GamepadMapping) -> GamepadInput {
+        GamepadInput {
+            mapping,
+            ..GamepadInput::default()
+        }
+    }
+
+    pub fn update(&mut self, state: PadState) -> Vec<KeyAction> {
+        let m = self.mapping;
+
+        let just_pressed = |button: usize| state.pressed(button) && !self.previous.pressed(button);
+
+        let mut actions = Vec::new();
+
+        for (button, dx, dy) in [(m.up, 0, -1), (m.down, 0, 1), (m.left, -1, 0), (m.right, 1, 0)] {
+            if just_pressed(button) {
+                actions.push(KeyAction::Move(dx, dy));
+            }
+        }
+
+        // screen y goes down like rows
+        let direction = |v: f32| if v > DEAD_ZONE { 1 } else if v < -DEAD_ZONE { -1 } else { 0 };
+        let stick = (direction(state.axis(m.stick_x)), direction(state.axis(m.stick_y)));
+
+        if stick != (0, 0) && stick != self.previous_stick {
+            actions.push(KeyAction::Move(stick.0, stick.1));
+        }
+
+        if just_pressed(m.hit) {
+            actions.push(KeyAction::Confirm);
+        }
+
+        if just_pressed(m.pause) {
+            actions.push(KeyAction::Pause);
+        }
+
+        self.previous = state;
+        self.previous_stick = stick;
+
+        actions
+    }
+}
+
+// First connected gamepad, None if there is no one
+pub fn poll_gamepad(navigator: &web_sys::Navigator) -> Option<PadState> {
+    let pads = navigator.get_gamepads().ok()?;
+
+    let pad = pads.iter()
+        .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+        .find(|pad| pad.connected())?;
+
+    let buttons = pad.buttons().iter()
+        .map(|button| button.dyn_into::<web_sys::GamepadButton>().map(|button| button.pressed()).unwrap_or(false))
+        .collect();
+
+    let axes = pad.axes().iter()
+        .map(|axis| axis.as_f64().unwrap_or(0.0) as f32)
+        .collect();
+
+    Some(PadState { buttons, axes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buttons(pressed: &[usize]) -> PadState {
+        let mut buttons = vec![false; 16];
+
+        for &button in pressed {
+            buttons[button] = true;
+        }
+
+        PadState { buttons, axes: vec![0.0; 2] }
+    }
+
+    fn stick(x: f32, y: f32) -> PadState {
+        PadState { buttons: vec![false; 16], axes: vec![x, y] }
+    }
+
+    fn feed(input: &mut GamepadInput, states: Vec<PadState>) -> Vec<Vec<KeyAction>> {
+        states.into_iter().map(|state| input.update(state)).collect()
+    }
+
+    #[test]
+    fn buttons_fire_on_press_only() {
+        let mut input = GamepadInput::new(GamepadMapping::default());
+
+        assert_eq!(feed(&mut input, vec![buttons(&[]), buttons(&[0]), buttons(&[0]), buttons(&[0]), buttons(&[]), buttons(&[0])]), vec![
+            vec![],
+            vec![KeyAction::Confirm],
+            // holding does not repeat
+            vec![],
+            vec![],
+            vec![],
+            vec![KeyAction::Confirm]
+        ]);
+    }
+
+    #[test]
+    fn dpad_and_buttons_together() {
+        let mut input = GamepadInput::new(GamepadMapping::default());
+
+        assert_eq!(input.update(buttons(&[12, 15, 0, 9])), vec![KeyAction::Move(0, -1), KeyAction::Move(1, 0), KeyAction::Confirm, KeyAction::Pause]);
+        // only the newly pressed one
+        assert_eq!(input.update(buttons(&[12, 15, 0, 9, 13])), vec![KeyAction::Move(0, 1)]);
+        assert_eq!(input.update(buttons(&[14])), vec![KeyAction::Move(-1, 0)]);
+    }
+
+    #[test]
+    fn stick_dead_zone() {
+        let mut input = GamepadInput::new(GamepadMapping::default());
+
+        assert_eq!(input.update(stick(DEAD_ZONE, -DEAD_ZONE)), vec![]);
+        assert_eq!(input.update(stick(0.3, 0.2)), vec![]);
+        assert_eq!(input.update(stick(DEAD_ZONE + 0.1, 0.2)), vec![KeyAction::Move(1, 0)]);
+        assert_eq!(input.update(stick(0.2, -0.9)), vec![KeyAction::Move(0, -1)]);
+    }
+
+    #[test]
+    fn stick_fires_once_per_tilt() {
+        let mut input = GamepadInput::new(GamepadMapping::default());
+
+        assert_eq!(feed(&mut input, vec![stick(0.9, 0.0), stick(1.0, 0.0), stick(0.7, 0.1), stick(0.0, 0.0), stick(0.8, 0.0)]), vec![
+            vec![KeyAction::Move(1, 0)],
+            // held in the same direction
+            vec![],
+            vec![],
+            // back to the center and tilted again
+            vec![],
+            vec![KeyAction::Move(1, 0)]
+        ]);
+
+        // a new direction fires without going through the center
+        assert_eq!(input.update(stick(0.8, 0.8)), vec![KeyAction::Move(1, 1)]);
+        assert_eq!(input.update(stick(-0.8, 0.0)), vec![KeyAction::Move(-1, 0)]);
+    }
+
+    #[test]
+    fn missing_buttons_and_axes_are_idle() {
+        let mut input = GamepadInput::new(GamepadMapping::default());
+
+        assert_eq!(input.update(PadState::default()), vec![]);
+        assert_eq!(input.update(PadState { buttons: vec![true], axes: vec![] }), vec![KeyAction::Confirm]);
+    }
+
+    #[test]
+    fn mapping_from_query() {
+        let mapping = GamepadMapping::from_query("?pad_hit=2&pad_stick_y=3&pad_pause=x&mode=slash");
+
+        assert_eq!(mapping, GamepadMapping { hit: 2, stick_y: 3, ..GamepadMapping::default() });
+
+        let mut input = GamepadInput::new(mapping);
+        assert_eq!(input.update(buttons(&[0])), vec![]);
+        assert_eq!(input.update(buttons(&[2])), vec![KeyAction::Confirm]);
+        assert_eq!(input.update(PadState { buttons: vec![], axes: vec![0.0, 0.0, 0.0, 1.0] }), vec![KeyAction::Move(0, 1)]);
+    }
+}
@@ -0,0 +1,254 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::math::*;
+use crate::collision::Segment;
+
+// Pointer further than this from the press point turns tap into swipe
+const TAP_DISTANCE: f32 = 0.05;
+// Seconds of holding still before long press fires
+const LONG_PRESS_TIME: f64 = 0.5;
+
+// Pointer position in clip space and time in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEvent {
+    Down(Point, f64),
+    Move(Point, f64),
+    Up(Point, f64),
+    // browser took the pointer over, e.g. for scrolling
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swipe {
+    // pointer positions from press to release
+    pub path: Vec<Point>,
+    // unit vector from the first point to the last one
+    pub direction: Vec2,
+    // clip space units per second
+    pub speed: f32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gesture {
+    Tap(Point),
+    LongPress(Point),
+    // piece of the path while pointer is dragged
+    Stroke(Segment),
+    // whole path on release
+    Swipe(Swipe),
+}
+
+// Turns stream of pointer events into gestures
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    // points with timestamps since press, empty when pointer is up
+    path: Vec<(Point, f64)>,
+    dragging: bool,
+    long_pressed: bool
+}
+
+impl GestureRecognizer {
+    pub fn new() -> GestureRecognizer {
+        GestureRecognizer::default()
+    }
+
+    pub fn handle(&mut self, event: PointerEvent) -> Option<Gesture> {
+        match event {
+            PointerEvent::Down(p, time) => {
+                self.path = vec![(p, time)];
+                self.dragging = false;
+                self.long_pressed = false;
+
+                None
+            },
+            PointerEvent::Move(p, time) => {
+                let &(last, _) = self.path.last()?;
+                let (start, _) = self.path[0];
+
+                self.path.push((p, time));
+
+                if !self.dragging && !self.long_pressed && start.distance(p) > TAP_DISTANCE {
+                    self.dragging = true;
+
+                    return Some(Gesture::Stroke(Segment::new(start, p)));
+                }
+
+                self.dragging.then(|| Gesture::Stroke(Segment::new(last, p)))
+            },
+            PointerEvent::Up(p, time) => {
+                if self.path.is_empty() {
+                    return None;
+                }
+
+                self.path.push((p, time));
+
+                let path = std::mem::take(&mut self.path);
+
+                if self.dragging {
+                    return Some(Gesture::Swipe(swipe(&path)));
+                }
+
+                (!self.long_pressed).then_some(Gesture::Tap(path[0].0))
+            },
+            // gesture is dropped, strokes already sent stay
+            PointerEvent::Cancel => {
+                self.path.clear();
+                self.dragging = false;
+                self.long_pressed = false;
+
+                None
+            }
+        }
+    }
+
+    // Long press fires while pointer is still held
+    pub fn update(&mut self, time: f64) -> Option<Gesture> {
+        let &(start, start_time) = self.path.first()?;
+
+        if self.dragging || self.long_pressed || time - start_time < LONG_PRESS_TIME {
+            return None;
+        }
+
+        self.long_pressed = true;
+
+        Some(Gesture::LongPress(start))
+    }
+}
+
+fn swipe(path: &[(Point, f64)]) -> Swipe {
+    let (first, start_time) = path[0];
+    let (last, end_time) = path[path.len() - 1];
+
+    let length: f32 = path.windows(2).map(|pair| pair[0].0.distance(pair[1].0)).sum();
+    let duration = (end_time - start_time).max(f64::EPSILON) as f32;
+
+    Swipe {
+        path: path.iter().map(|&(p, _)| p).collect(),
+        direction: (last - first).normalize(),
+        speed: length / duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_drops_swipe() {
+        let mut recognizer = GestureRecognizer::new();
+
+        recognizer.handle(PointerEvent::Down(Point::new(0.0, 0.0), 0.0));
+        assert!(matches!(recognizer.handle(PointerEvent::Move(Point::new(0.5, 0.0), 0.1)), Some(Gesture::Stroke(_))));
+
+        assert_eq!(recognizer.handle(PointerEvent::Cancel), None);
+        assert_eq!(recognizer.handle(PointerEvent::Move(Point::new(0.8, 0.0), 0.2)), None);
+        assert_eq!(recognizer.handle(PointerEvent::Up(Point::new(0.8, 0.0), 0.3)), None);
+    }
+
+    #[test]
+    fn cancel_drops_tap_and_long_press() {
+        let mut recognizer = GestureRecognizer::new();
+
+        recognizer.handle(PointerEvent::Down(Point::new(0.0, 0.0), 0.0));
+        assert_eq!(recognizer.handle(PointerEvent::Cancel), None);
+
+        assert_eq!(recognizer.update(LONG_PRESS_TIME + 1.0), None);
+        assert_eq!(recognizer.handle(PointerEvent::Up(Point::new(0.0, 0.0), 0.1)), None);
+
+        // next press works as usual
+        recognizer.handle(PointerEvent::Down(Point::new(0.0, 0.0), 2.0));
+        assert_eq!(recognizer.handle(PointerEvent::Up(Point::new(0.0, 0.0), 2.1)), Some(Gesture::Tap(Point::new(0.0, 0.0))));
+    }
+
+    fn press(recognizer: &mut GestureRecognizer, path: &[(f32, f32, f64)]) -> Vec<Gesture> {
+        let (x, y, time) = path[0];
+        let mut gestures: Vec<Gesture> = recognizer.handle(PointerEvent::Down(Point::new(x, y), time)).into_iter().collect();
+
+        for &(x, y, time) in &path[1..path.len() - 1] {
+            gestures.extend(recognizer.handle(PointerEvent::Move(Point::new(x, y), time)));
+        }
+
+        let (x, y, time) = path[path.len() - 1];
+        gestures.extend(recognizer.handle(PointerEvent::Up(Point::new(x, y), time)));
+
+        gestures
+    }
+
+    #[test]
+    fn tap_within_tap_distance() {
+        let mut recognizer = GestureRecognizer::new();
+
+        let gestures = press(&mut recognizer, &[(0.0, 0.0, 0.0), (TAP_DISTANCE, 0.0, 0.1), (TAP_DISTANCE, 0.0, 0.2)]);
+        assert_eq!(gestures, vec![Gesture::Tap(Point::new(0.0, 0.0))]);
+    }
+
+    #[test]
+    fn swipe_past_tap_distance() {
+        let mut recognizer = GestureRecognizer::new();
+
+        let beyond = TAP_DISTANCE + 0.01;
+        let gestures = press(&mut recognizer, &[(0.0, 0.0, 0.0), (beyond, 0.0, 0.1), (beyond, 0.0, 0.2)]);
+
+        assert_eq!(gestures.len(), 2);
+        assert_eq!(gestures[0], Gesture::Stroke(Segment::new(Point::new(0.0, 0.0), Point::new(beyond, 0.0))));
+        assert!(matches!(gestures[1], Gesture::Swipe(_)));
+    }
+
+    #[test]
+    fn swipe_direction_and_speed() {
+        let mut recognizer = GestureRecognizer::new();
+
+        // 0.3 up and 0.4 right in two pieces, 0.5 long in 0.25 seconds
+        let gestures = press(&mut recognizer, &[(0.0, 0.0, 1.0), (0.15, 0.2, 1.1), (0.3, 0.4, 1.25)]);
+
+        let Some(Gesture::Swipe(swipe)) = gestures.last() else {
+            panic!("No swipe in {:?}", gestures);
+        };
+
+        assert_eq!(swipe.path, vec![Point::new(0.0, 0.0), Point::new(0.15, 0.2), Point::new(0.3, 0.4)]);
+        assert!(swipe.direction.distance(Vec2::new(0.6, 0.8)) < 1e-5);
+        assert!((swipe.speed - 2.0).abs() < 1e-4);
+
+        // speed goes along the path, not the straight line
+        let gestures = press(&mut recognizer, &[(0.0, 0.0, 0.0), (0.5, 0.0, 0.5), (0.0, 0.0, 1.0)]);
+        let Some(Gesture::Swipe(back)) = gestures.last() else {
+            panic!("No swipe in {:?}", gestures);
+        };
+
+        assert!((back.speed - 1.0).abs() < 1e-4);
+        assert_eq!(back.direction, Vec2::zero());
+    }
+
+    #[test]
+    fn long_press_after_holding_still() {
+        let mut recognizer = GestureRecognizer::new();
+
+        recognizer.handle(PointerEvent::Down(Point::new(0.1, 0.2), 1.0));
+        assert_eq!(recognizer.update(1.0 + LONG_PRESS_TIME / 2.0), None);
+
+        // small moves do not break the press
+        assert_eq!(recognizer.handle(PointerEvent::Move(Point::new(0.1 + TAP_DISTANCE / 2.0, 0.2), 1.2)), None);
+
+        assert_eq!(recognizer.update(1.0 + LONG_PRESS_TIME), Some(Gesture::LongPress(Point::new(0.1, 0.2))));
+        // fires once and takes the tap
+        assert_eq!(recognizer.update(1.0 + 2.0*LONG_PRESS_TIME), None);
+        assert_eq!(recognizer.handle(PointerEvent::Move(Point::new(0.5, 0.2), 1.7)), None);
+        assert_eq!(recognizer.handle(PointerEvent::Up(Point::new(0.5, 0.2), 1.8)), None);
+    }
+
+    #[test]
+    fn no_long_press_while_dragging_or_released() {
+        let mut recognizer = GestureRecognizer::new();
+
+        assert_eq!(recognizer.update(10.0), None);
+
+        recognizer.handle(PointerEvent::Down(Point::new(0.0, 0.0), 0.0));
+        recognizer.handle(PointerEvent::Move(Point::new(0.5, 0.0), 0.1));
+        assert_eq!(recognizer.update(LONG_PRESS_TIME + 0.1), None);
+
+        recognizer.handle(PointerEvent::Up(Point::new(0.5, 0.0), 0.2));
+        recognizer.handle(PointerEvent::Down(Point::new(0.0, 0.0), 1.0));
+        recognizer.handle(PointerEvent::Up(Point::new(0.0, 0.0), 1.1));
+        assert_eq!(recognizer.update(1.0 + LONG_PRESS_TIME), None);
+    }
+}
@@ -0,0 +1,38 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::events::*;
+
+// Vibration lengths in milliseconds
+const HIT_VIBRATION: u32 = 20;
+const DECOY_VIBRATION: u32 = 60;
+const BOMB_VIBRATION: u32 = 250;
+
+// Phone vibrates on hits, browsers without vibration api just ignore it
+// disabled with `?haptics=0`
+#[derive(Debug)]
+pub struct Haptics {
+    navigator: web_sys::Navigator
+}
+
+impl Haptics {
+    pub fn new(navigator: web_sys::Navigator) -> Haptics {
+        Haptics {
+            navigator
+        }
+    }
+
+    fn vibrate(&self, duration: u32) {
+        self.navigator.vibrate_with_duration(duration);
+    }
+}
+
+impl Subscriber for Haptics {
+    fn on_event(&mut self, event: &GameEvent, _follow_ups: &mut Vec<GameEvent>) {
+        match event {
+            GameEvent::TargetHit { .. } => self.vibrate(HIT_VIBRATION),
+            GameEvent::DecoyHit => self.vibrate(DECOY_VIBRATION),
+            GameEvent::BombHit => self.vibrate(BOMB_VIBRATION),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,124 @@
+#[allow(unused_imports)]
+use crate::log;
+
+// What a pressed key means for the game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    // hit the hole with this number at once
+    Hit(usize),
+    // move selection by columns and rows
+    Move(i32, i32),
+    // hit the selected hole
+    Confirm,
+    ToggleAccessible,
+    ToggleMute,
+    ToggleLeaderboard,
+    ToggleSettings,
+    // pause or resume the game, close overlays
+    Pause,
+    // change round time by the given seconds
+    RoundTime(f64),
+}
+
+// Step of round time change in seconds
+const ROUND_TIME_STEP: f64 = 0.5;
+
+// `key` is KeyboardEvent.key
+pub fn key_action(key: &str) -> Option<KeyAction> {
+    // letters come in upper case with Shift or Caps Lock
+    let key = if key.chars().count() == 1 { key.to_ascii_lowercase() } else { String::from(key) };
+
+    match key.as_str() {
+        "ArrowUp" | "w" => Some(KeyAction::Move(0, -1)),
+        "ArrowDown" | "s" => Some(KeyAction::Move(0, 1)),
+        "ArrowLeft" | "a" => Some(KeyAction::Move(-1, 0)),
+        "ArrowRight" | "d" => Some(KeyAction::Move(1, 0)),
+        "Enter" | " " => Some(KeyAction::Confirm),
+        "t" => Some(KeyAction::ToggleAccessible),
+        "m" => Some(KeyAction::ToggleMute),
+        "l" => Some(KeyAction::ToggleLeaderboard),
+        "o" => Some(KeyAction::ToggleSettings),
+        "Escape" | "p" => Some(KeyAction::Pause),
+        "+" | "=" => Some(KeyAction::RoundTime(ROUND_TIME_STEP)),
+        "-" => Some(KeyAction::RoundTime(-ROUND_TIME_STEP)),
+        _ => {
+            // keys 1..9 are holes in reading order
+            let digit = key.parse::<usize>().ok().filter(|d| (1..=9).contains(d))?;
+
+            Some(KeyAction::Hit(digit - 1))
+        }
+    }
+}
+
+// Selected hole number in grid, moves stop at the edges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoleCursor {
+    columns: usize,
+    rows: usize,
+    // None until the first move
+    selected: Option<usize>
+}
+
+impl HoleCursor {
+    pub fn new(columns: usize, rows: usize) -> HoleCursor {
+        HoleCursor {
+            columns,
+            rows,
+            selected: None
+        }
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, number: usize) {
+        if number < self.columns*self.rows {
+            self.selected = Some(number);
+        }
+    }
+
+    // The first move selects the top left hole
+    pub fn move_by(&mut self, dx: i32, dy: i32) -> usize {
+        let number = match self.selected {
+            Some(number) => {
+                let column = (number % self.columns) as i32 + dx;
+                let row = (number / self.columns) as i32 + dy;
+
+                let column = column.clamp(0, self.columns as i32 - 1) as usize;
+                let row = row.clamp(0, self.rows as i32 - 1) as usize;
+
+                row*self.columns + column
+            },
+            None => 0
+        };
+
+        self.selected = Some(number);
+
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upper_case_letters_work() {
+        for key in ["w", "W", "t", "T", "m", "M", "l", "L", "o", "O", "p", "P"] {
+            assert_eq!(key_action(key), key_action(&key.to_lowercase()));
+            assert!(key_action(key).is_some(), "{key}");
+        }
+
+        assert_eq!(key_action("D"), Some(KeyAction::Move(1, 0)));
+        assert_eq!(key_action("P"), Some(KeyAction::Pause));
+    }
+
+    #[test]
+    fn named_keys_keep_their_case() {
+        assert_eq!(key_action("Escape"), Some(KeyAction::Pause));
+        assert_eq!(key_action("ESCAPE"), None);
+        assert_eq!(key_action("5"), Some(KeyAction::Hit(4)));
+        assert_eq!(key_action("0"), None);
+    }
+}
@@ -0,0 +1,363 @@
+use wasm_bindgen::prelude::*;
+
+#[allow(unused_imports)]
+use crate::log;
+
+use serde::{Deserialize, Serialize};
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+// Page url parameters our bot adds to the game link,
+// the backend needs them to call getGameHighScores for the right message
+const SESSION_PARAMS: &[&str] = &["user_id", "chat_id", "message_id", "inline_message_id"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub rank: u32,
+    pub name: String,
+    pub score: u64
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub top: Vec<Entry>,
+    // None if the player has no score yet
+    pub player: Option<Entry>,
+    // players of the chat the game was shared in, empty outside Telegram
+    pub friends: Vec<Entry>
+}
+
+// What leaderboard screen shows
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaderboardView {
+    Loading,
+    Failed(String),
+    Ready(Leaderboard),
+}
+
+impl From<Result<Leaderboard, String>> for LeaderboardView {
+    fn from(result: Result<Leaderboard, String>) -> Self {
+        match result {
+            Ok(leaderboard) => LeaderboardView::Ready(leaderboard),
+            Err(error) => LeaderboardView::Failed(error)
+        }
+    }
+}
+
+impl LeaderboardView {
+    // Text of the leaderboard panel, the player is marked
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = vec![String::from("Leaderboard"), String::new()];
+
+        match self {
+            LeaderboardView::Loading => lines.push(String::from("Loading...")),
+            LeaderboardView::Failed(_) => lines.push(String::from("Leaderboard is unavailable")),
+            LeaderboardView::Ready(board) => {
+                let line = |entry: &Entry| format!("{}. {}   {}", entry.rank, entry.name, entry.score);
+
+                lines.extend(board.top.iter().map(|entry| {
+                    if board.player.as_ref() == Some(entry) { format!("> {} <", line(entry)) } else { line(entry) }
+                }));
+
+                match &board.player {
+                    Some(player) if !board.top.contains(player) => {
+                        lines.push(String::from("..."));
+                        lines.push(format!("> {} <", line(player)));
+                    },
+                    None => lines.push(String::from("Play to get into the leaderboard")),
+                    _ => {}
+                }
+
+                if !board.friends.is_empty() {
+                    lines.push(String::new());
+                    lines.push(String::from("In this chat"));
+                    lines.extend(board.friends.iter().map(line));
+                }
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("Tap or L to close"));
+
+        lines
+    }
+}
+
+pub type OnLeaderboard = Box<dyn FnOnce(Result<Leaderboard, String>)>;
+
+// Source of leaderboard, `on_done` may be called later than `fetch` returns
+pub trait LeaderboardClient: std::fmt::Debug {
+    fn fetch(&self, limit: usize, on_done: OnLeaderboard);
+    fn submit(&self, score: u64);
+}
+
+// Talks to our bot backend
+// GET  <base>/leaderboard?limit=N&<session> returns Leaderboard json
+// POST <base>/score?<session> with {"score": N}
+#[derive(Debug)]
+pub struct HttpClient {
+    base_url: String,
+    session: String
+}
+
+impl HttpClient {
+    // Session params are taken from the page url query
+    pub fn new(base_url: &str, query: &str) -> HttpClient {
+        let session = query.trim_start_matches('?')
+            .split('&')
+            .filter(|pair| pair.split_once('=').is_some_and(|(name, _)| SESSION_PARAMS.contains(&name)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        HttpClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            session
+        }
+    }
+
+    // Session params go after the given ones, there are none outside Telegram
+    fn url(&self, path: &str, query: &str) -> String {
+        let params: Vec<&str> = [query, self.session.as_str()].into_iter().filter(|part| !part.is_empty()).collect();
+
+        if params.is_empty() {
+            format!("{}/{}", self.base_url, path)
+        } else {
+            format!("{}/{}?{}", self.base_url, path, params.join("&"))
+        }
+    }
+}
+
+impl LeaderboardClient for HttpClient {
+    fn fetch(&self, limit: usize, on_done: OnLeaderboard) {
+        let url = self.url("leaderboard", &format!("limit={}", limit));
+
+        request(&url, "GET", None, Box::new(move |result| {
+            on_done(result.and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string())));
+        }));
+    }
+
+    fn submit(&self, score: u64) {
+        let url = self.url("score", "");
+        let body = serde_json::json!({ "score": score }).to_string();
+
+        request(&url, "POST", Some(&body), Box::new(|result| {
+            if let Err(error) = result {
+                log::write(&format!("Failed to submit score: {}", error));
+            }
+        }));
+    }
+}
+
+// Keeps leaderboard in memory, used without backend and in native builds
+#[derive(Debug, Default)]
+pub struct MockClient {
+    board: RefCell<Leaderboard>
+}
+
+impl MockClient {
+    pub fn new(board: Leaderboard) -> MockClient {
+        MockClient {
+            board: RefCell::new(board)
+        }
+    }
+}
+
+impl LeaderboardClient for MockClient {
+    fn fetch(&self, limit: usize, on_done: OnLeaderboard) {
+        let mut board = self.board.borrow().clone();
+        board.top.truncate(limit);
+
+        on_done(Ok(board));
+    }
+
+    // Player entry takes its place among the top ones
+    fn submit(&self, score: u64) {
+        let mut board = self.board.borrow_mut();
+
+        let name = board.player.as_ref().map_or_else(|| String::from("You"), |player| player.name.clone());
+
+        if board.player.as_ref().is_some_and(|player| player.score >= score) {
+            return;
+        }
+
+        board.top.retain(|entry| entry.name != name);
+        board.top.push(Entry { rank: 0, name: name.clone(), score });
+        board.top.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+
+        for (i, entry) in board.top.iter_mut().enumerate() {
+            entry.rank = i as u32 + 1;
+        }
+
+        board.player = board.top.iter().find(|entry| entry.name == name).cloned();
+    }
+}
+
+type Done = Rc<dyn Fn(Result<String, String>)>;
+
+// fetch() with response text or error message passed to `on_done`
+fn request(url: &str, method: &str, body: Option<&str>, on_done: Box<dyn FnOnce(Result<String, String>)>) {
+    let window = web_sys::window().expect("Failed to get window");
+
+    let init = web_sys::RequestInit::new();
+    init.set_method(method);
+
+    if let Some(body) = body {
+        init.set_body(&JsValue::from_str(body));
+        init.set_headers_headers(&json_headers());
+    }
+
+    // both success and failure paths hold it, only the first call counts
+    let on_done = RefCell::new(Some(on_done));
+    let done: Done = Rc::new(move |result| {
+        if let Some(on_done) = on_done.borrow_mut().take() {
+            on_done(result);
+        }
+    });
+
+    let fetched = window.fetch_with_str_and_init(url, &init);
+
+    then(&fetched, done, |response, done| {
+        let response: web_sys::Response = response.unchecked_into();
+
+        if !response.ok() {
+            done(Err(format!("HTTP {}", response.status())));
+            return;
+        }
+
+        match response.text() {
+            Ok(text) => then(&text, done, |text, done| done(text.as_string().ok_or_else(|| String::from("Response is not text")))),
+            Err(error) => done(Err(format!("{:?}", error)))
+        }
+    });
+}
+
+fn json_headers() -> web_sys::Headers {
+    let headers = web_sys::Headers::new().expect("Failed to create headers");
+    headers.set("Content-Type", "application/json").expect("Failed to set header");
+
+    headers
+}
+
+// Calls `on_value` when promise resolves and `done` with error when it rejects
+fn then<F: FnOnce(JsValue, Done) + 'static>(promise: &js_sys::Promise, done: Done, on_value: F) {
+    let fail = done.clone();
+
+    let resolve = Closure::once(move |value: JsValue| on_value(value, done));
+    let reject = Closure::once(move |error: JsValue| fail(Err(format!("{:?}", error))));
+
+    let _ = promise.then2(&resolve, &reject);
+
+    // promise callbacks live until they are called
+    resolve.forget();
+    reject.forget();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(rank: u32, name: &str, score: u64) -> Entry {
+        Entry { rank, name: String::from(name), score }
+    }
+
+    // What the leaderboard screen shows after fetching from the client
+    fn fetch(client: &dyn LeaderboardClient, limit: usize) -> LeaderboardView {
+        let view = Rc::new(RefCell::new(LeaderboardView::Loading));
+        let result = view.clone();
+
+        client.fetch(limit, Box::new(move |board| *result.borrow_mut() = LeaderboardView::from(board)));
+
+        view.replace(LeaderboardView::Loading)
+    }
+
+    fn board() -> Leaderboard {
+        Leaderboard {
+            top: vec![entry(1, "Ann", 900), entry(2, "Bob", 700), entry(3, "Kim", 500)],
+            player: Some(entry(2, "Bob", 700)),
+            friends: Vec::new()
+        }
+    }
+
+    #[test]
+    fn player_in_top_is_marked() {
+        let lines = fetch(&MockClient::new(board()), 10).lines();
+
+        assert_eq!(lines, vec![
+            "Leaderboard", "",
+            "1. Ann   900", "> 2. Bob   700 <", "3. Kim   500",
+            "", "Tap or L to close"
+        ]);
+    }
+
+    #[test]
+    fn player_outside_top_goes_last() {
+        let client = MockClient::new(Leaderboard { player: Some(entry(3, "Kim", 500)), ..board() });
+        let lines = fetch(&client, 2).lines();
+
+        assert_eq!(lines, vec![
+            "Leaderboard", "",
+            "1. Ann   900", "2. Bob   700", "...", "> 3. Kim   500 <",
+            "", "Tap or L to close"
+        ]);
+    }
+
+    #[test]
+    fn friends_are_listed_when_there_are_some() {
+        let lines = fetch(&MockClient::new(board()), 10).lines();
+        assert!(!lines.contains(&String::from("In this chat")));
+
+        let client = MockClient::new(Leaderboard { friends: vec![entry(1, "Bob", 700), entry(2, "Eve", 300)], ..board() });
+        let lines = fetch(&client, 10).lines();
+
+        assert_eq!(lines[lines.len() - 6..], ["", "In this chat", "1. Bob   700", "2. Eve   300", "", "Tap or L to close"]);
+    }
+
+    #[test]
+    fn player_without_score_is_invited() {
+        let lines = fetch(&MockClient::new(Leaderboard::default()), 10).lines();
+
+        assert_eq!(lines, vec!["Leaderboard", "", "Play to get into the leaderboard", "", "Tap or L to close"]);
+    }
+
+    #[test]
+    fn failed_fetch_is_shown() {
+        let view = LeaderboardView::from(Err(String::from("HTTP 500")));
+
+        assert_eq!(view, LeaderboardView::Failed(String::from("HTTP 500")));
+        assert_eq!(view.lines()[2], "Leaderboard is unavailable");
+        assert_eq!(LeaderboardView::Loading.lines()[2], "Loading...");
+    }
+
+    #[test]
+    fn submitted_score_takes_its_place() {
+        let client = MockClient::new(Leaderboard { player: None, ..board() });
+
+        client.submit(800);
+        client.submit(100);
+
+        let LeaderboardView::Ready(board) = fetch(&client, 10) else {
+            panic!("Mock client never fails");
+        };
+
+        assert_eq!(board.player, Some(entry(2, "You", 800)));
+        assert_eq!(board.top.iter().map(|entry| entry.rank).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn urls_keep_session_params() {
+        let client = HttpClient::new("https://example.com/api/", "?user_id=1&debug=1&chat_id=2");
+
+        assert_eq!(client.url("leaderboard", "limit=10"), "https://example.com/api/leaderboard?limit=10&user_id=1&chat_id=2");
+        assert_eq!(client.url("score", ""), "https://example.com/api/score?user_id=1&chat_id=2");
+    }
+
+    #[test]
+    fn urls_without_session_have_no_dangling_separators() {
+        let client = HttpClient::new("https://example.com/api", "?debug=1");
+
+        assert_eq!(client.url("leaderboard", "limit=10"), "https://example.com/api/leaderboard?limit=10");
+        assert_eq!(client.url("score", ""), "https://example.com/api/score");
+    }
+}
@@ -1,38 +1,100 @@
 mod point;
+mod math;
 mod shape;
 mod shader;
 mod log;
 mod render;
 mod game;
 mod rand;
+mod util;
 mod ui;
 mod game_state;
+mod game_mode;
+mod scoring;
+mod special;
+mod spawner;
 mod texture;
+mod atlas;
+mod animation;
+mod tween;
+mod transform;
+mod node;
+mod ecs;
+mod systems;
 mod scene;
+mod collision;
+mod gesture;
+mod keyboard;
+mod announcer;
+mod gamepad;
+mod audio;
+mod synth;
+mod persistence;
+mod save;
+mod leaderboard;
+mod events;
+mod achievements;
+mod haptics;
+mod screen;
 
 use wasm_bindgen::prelude::*;
 
 use point::*;
 use game::*;
+use gesture::*;
 
 use std::rc::Rc;
 use std::cell::RefCell;
 
+// Wraps pointer position and time into event
+type PointerEventFn = fn(Point, f64) -> PointerEvent;
+
 fn set_input_callback(game: Rc<RefCell<Game>>) {
+    let events: [(&str, PointerEventFn); 4] = [
+        ("pointerdown", PointerEvent::Down),
+        ("pointermove", PointerEvent::Move),
+        ("pointerup", PointerEvent::Up),
+        ("pointercancel", |_, _| PointerEvent::Cancel)
+    ];
+
     let window = game.as_ref().borrow().window();
 
-    let callback = Closure::wrap(Box::new(move |event: web_sys::Event| {
-        let wnd = game.as_ref().borrow().window();
+    for (name, make_event) in events {
+        let game = game.clone();
+
+        let callback = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let wnd = game.as_ref().borrow().window();
+
+            let width: f32 = wnd.inner_width().expect("Failed to get window width").as_f64().unwrap() as f32;
+            let height: f32 = wnd.inner_height().expect("Failed to get window height").as_f64().unwrap() as f32;
+
+            let e = event.dyn_into::<web_sys::PointerEvent>().expect("Failed to get pointer event");
+
+            let point = Point::from_screen_coords(e.x() as f32 / width, e.y() as f32 / height);
 
-        let width: f32 = wnd.inner_width().expect("Failed to get window width").as_f64().unwrap() as f32;
-        let height: f32 = wnd.inner_height().expect("Failed to get window height").as_f64().unwrap() as f32;
+            // same clock as performance.now()
+            game.as_ref().borrow_mut().store_input(make_event(point, e.time_stamp() / 1000.0));
+        }) as Box<dyn FnMut(_)>);
 
-        let e = event.dyn_into::<web_sys::MouseEvent>().expect("Failed to get mouse event");
+        window.add_event_listener_with_callback(name, callback.as_ref().unchecked_ref()).expect("Failed to set event listener");
+
+        callback.forget();
+    }
+}
+
+fn set_key_callback(game: Rc<RefCell<Game>>) {
+    let window = game.as_ref().borrow().window();
+
+    let callback = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        let e = event.dyn_into::<web_sys::KeyboardEvent>().expect("Failed to get keyboard event");
 
-        game.as_ref().borrow_mut().store_input(Point::from_screen_coords(e.x() as f32 / width, e.y() as f32 / height));
+        // holding a key does not repeat hits
+        if !e.repeat() {
+            game.as_ref().borrow_mut().store_key(e.key());
+        }
     }) as Box<dyn FnMut(_)>);
 
-    window.add_event_listener_with_callback("click", callback.as_ref().unchecked_ref()).expect("Failed to set event listener");
+    window.add_event_listener_with_callback("keydown", callback.as_ref().unchecked_ref()).expect("Failed to set event listener");
 
     callback.forget();
 }
@@ -62,6 +124,7 @@ pub fn start() -> Result<(), JsValue> {
     let game: Rc<RefCell<Game>> = Rc::new(RefCell::new(Game::new()));
 
     set_input_callback(game.clone());
+    set_key_callback(game.clone());
     run_loop(game.clone());
 
     Ok(())
@@ -0,0 +1,498 @@
+#[allow(unused_imports)]
+use crate::log;
+pub use crate::point::Point;
+
+use std::ops::Mul;
+
+pub type Vec2 = Point;
+
+// 3x3 matrix in column order for 2d homogeneous coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    data: [f32; 9]
+}
+
+impl Mat3 {
+    pub fn from_columns(data: [f32; 9]) -> Mat3 {
+        Mat3 {
+            data
+        }
+    }
+
+    pub fn id() -> Mat3 {
+        Mat3::from_columns([
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 1.0
+        ])
+    }
+
+    pub fn translation(v: Vec2) -> Mat3 {
+        Mat3::from_columns([
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            v.x(), v.y(), 1.0
+        ])
+    }
+
+    pub fn scale(v: Vec2) -> Mat3 {
+        Mat3::from_columns([
+            v.x(), 0.0, 0.0,
+            0.0, v.y(), 0.0,
+            0.0, 0.0, 1.0
+        ])
+    }
+
+    // counterclockwise
+    pub fn rotation(angle: f32) -> Mat3 {
+        let (sin, cos) = angle.sin_cos();
+
+        Mat3::from_columns([
+            cos, sin, 0.0,
+            -sin, cos, 0.0,
+            0.0, 0.0, 1.0
+        ])
+    }
+
+    // row and column
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.data[3*column + row]
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let m = |r, c| self.get(r, c);
+
+        m(0, 0)*(m(1, 1)*m(2, 2) - m(1, 2)*m(2, 1))
+            - m(0, 1)*(m(1, 0)*m(2, 2) - m(1, 2)*m(2, 0))
+            + m(0, 2)*(m(1, 0)*m(2, 1) - m(1, 1)*m(2, 0))
+    }
+
+    // None for degenerate matrices, e.g. zero scale
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let m = |r, c| self.get(r, c);
+
+        // cofactor of (r, c) divided by determinant goes to (c, r)
+        let mut data = [0.0; 9];
+
+        for r in 0..3 {
+            for c in 0..3 {
+                let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+                let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+
+                let cofactor = m(r1, c1)*m(r2, c2) - m(r1, c2)*m(r2, c1);
+
+                data[3*r + c] = cofactor / det;
+            }
+        }
+
+        Some(Mat3::from_columns(data))
+    }
+
+    pub fn transform_point(&self, p: Vec2) -> Vec2 {
+        Vec2::new(
+            self.get(0, 0)*p.x() + self.get(0, 1)*p.y() + self.get(0, 2),
+            self.get(1, 0)*p.x() + self.get(1, 1)*p.y() + self.get(1, 2)
+        )
+    }
+
+    // ignores translation
+    #[allow(unused)]
+    pub fn transform_vector(&self, v: Vec2) -> Vec2 {
+        Vec2::new(
+            self.get(0, 0)*v.x() + self.get(0, 1)*v.y(),
+            self.get(1, 0)*v.x() + self.get(1, 1)*v.y()
+        )
+    }
+
+    // embeds 2d transform into xy plane
+    pub fn to_mat4(self) -> Mat4 {
+        let m = |r, c| self.get(r, c);
+
+        Mat4::from_columns([
+            m(0, 0), m(1, 0), 0.0, 0.0,
+            m(0, 1), m(1, 1), 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            m(0, 2), m(1, 2), 0.0, 1.0
+        ])
+    }
+}
+
+// self after other: (a*b)(p) = a(b(p))
+impl Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: Mat3) -> Mat3 {
+        let mut data = [0.0; 9];
+
+        for c in 0..3 {
+            for r in 0..3 {
+                data[3*c + r] = (0..3).map(|k| self.get(r, k)*other.get(k, c)).sum();
+            }
+        }
+
+        Mat3::from_columns(data)
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Mat3::id()
+    }
+}
+
+// 4x4 matrix in column order as shaders expect it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    data: [f32; 16]
+}
+
+impl Mat4 {
+    pub fn from_columns(data: [f32; 16]) -> Mat4 {
+        Mat4 {
+            data
+        }
+    }
+
+    pub fn id() -> Mat4 {
+        Mat3::id().to_mat4()
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.data[4*column + row]
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut data = [0.0; 16];
+
+        for c in 0..4 {
+            for r in 0..4 {
+                data[4*c + r] = (0..4).map(|k| self.get(r, k)*other.get(k, c)).sum();
+            }
+        }
+
+        Mat4::from_columns(data)
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::id()
+    }
+}
+
+// Axis aligned bounding box, min and max corners are inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2
+}
+
+#[allow(unused)]
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Aabb {
+        Aabb {
+            min: min.min(max),
+            max: min.max(max)
+        }
+    }
+
+    // None for empty slice
+    pub fn from_points(points: &[Vec2]) -> Option<Aabb> {
+        let first = *points.first()?;
+
+        Some(points.iter().fold(Aabb { min: first, max: first }, |aabb, &p| Aabb { min: aabb.min.min(p), max: aabb.max.max(p) }))
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.min.x() <= p.x() && p.x() <= self.max.x() && self.min.y() <= p.y() && p.y() <= self.max.y()
+    }
+
+    pub fn contains_aabb(&self, other: &Aabb) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x() <= other.max.x() && other.min.x() <= self.max.x() && self.min.y() <= other.max.y() && other.min.y() <= self.max.y()
+    }
+
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        Some(Aabb { min: self.min.max(other.min), max: self.max.min(other.max) })
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const EPS: f32 = 1e-5;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < EPS, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_mat3_near(a: Mat3, b: Mat3) {
+        for r in 0..3 {
+            for c in 0..3 {
+                assert!((a.get(r, c) - b.get(r, c)).abs() < EPS, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn vec2_operators() {
+        let (a, b) = (Vec2::new(1.0, 2.0), Vec2::new(3.0, -4.0));
+
+        assert_eq!(a + b, Vec2::new(4.0, -2.0));
+        assert_eq!(a - b, Vec2::new(-2.0, 6.0));
+        assert_eq!(a*2.0, Vec2::new(2.0, 4.0));
+        assert_eq!(b / 2.0, Vec2::new(1.5, -2.0));
+        assert_eq!(-a, Vec2::new(-1.0, -2.0));
+
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+        c -= b;
+        assert_eq!(c, a);
+    }
+
+    #[test]
+    fn vec2_products() {
+        let (x, y) = (Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0));
+
+        assert_eq!(Vec2::new(1.0, 2.0).dot(Vec2::new(3.0, -4.0)), -5.0);
+        assert_eq!(x.dot(y), 0.0);
+
+        // counterclockwise is positive
+        assert_eq!(x.cross(y), 1.0);
+        assert_eq!(y.cross(x), -1.0);
+        assert_eq!(x.cross(x*3.0), 0.0);
+
+        assert_eq!(x.perp(), y);
+    }
+
+    #[test]
+    fn vec2_length_and_normalize() {
+        let v = Vec2::new(3.0, 4.0);
+
+        assert_eq!(v.length_squared(), 25.0);
+        assert_eq!(v.length(), 5.0);
+        assert_eq!(v.distance(Vec2::zero()), 5.0);
+        assert_near(v.normalize(), Vec2::new(0.6, 0.8));
+        assert!((v.normalize().length() - 1.0).abs() < EPS);
+
+        // zero length stays zero instead of NaN
+        assert_eq!(Vec2::zero().normalize(), Vec2::zero());
+    }
+
+    #[test]
+    fn vec2_lerp() {
+        let (a, b) = (Vec2::new(-1.0, 2.0), Vec2::new(3.0, 6.0));
+
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vec2::new(1.0, 4.0));
+        // not clamped
+        assert_eq!(a.lerp(b, 2.0), Vec2::new(7.0, 10.0));
+    }
+
+    #[test]
+    fn vec2_screen_coords() {
+        assert_eq!(Vec2::from_screen_coords(0.0, 0.0), Vec2::new(-1.0, 1.0));
+        assert_eq!(Vec2::from_screen_coords(0.5, 0.5), Vec2::zero());
+        assert_eq!(Vec2::from_screen_coords(1.0, 1.0), Vec2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn mat3_transforms() {
+        let p = Vec2::new(1.0, 0.0);
+
+        assert_eq!(Mat3::translation(Vec2::new(2.0, 3.0)).transform_point(p), Vec2::new(3.0, 3.0));
+        assert_eq!(Mat3::scale(Vec2::new(2.0, 3.0)).transform_point(Vec2::new(1.0, 1.0)), Vec2::new(2.0, 3.0));
+        assert_near(Mat3::rotation(PI / 2.0).transform_point(p), Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn mat3_multiply() {
+        let t = Mat3::translation(Vec2::new(1.0, 0.0));
+        let r = Mat3::rotation(PI / 2.0);
+
+        assert_eq!(t*Mat3::id(), t);
+        assert_eq!(Mat3::id()*t, t);
+
+        // right one goes first
+        assert_near((t*r).transform_point(Vec2::new(1.0, 0.0)), Vec2::new(1.0, 1.0));
+        assert_near((r*t).transform_point(Vec2::new(1.0, 0.0)), Vec2::new(0.0, 2.0));
+    }
+
+    #[test]
+    fn mat3_inverse() {
+        let m = Mat3::translation(Vec2::new(2.0, -1.0))*Mat3::rotation(0.7)*Mat3::scale(Vec2::new(2.0, 0.5));
+        let inverse = m.inverse().unwrap();
+
+        assert_mat3_near(m*inverse, Mat3::id());
+        assert_mat3_near(inverse*m, Mat3::id());
+
+        let p = Vec2::new(0.3, -0.8);
+        assert_near(inverse.transform_point(m.transform_point(p)), p);
+
+        assert_eq!(Mat3::id().inverse(), Some(Mat3::id()));
+    }
+
+    #[test]
+    fn mat3_singular_has_no_inverse() {
+        assert_eq!(Mat3::scale(Vec2::new(0.0, 1.0)).inverse(), None);
+        assert_eq!(Mat3::from_columns([0.0; 9]).inverse(), None);
+        // columns are parallel
+        assert_eq!(Mat3::from_columns([1.0, 2.0, 0.0, 2.0, 4.0, 0.0, 0.0, 0.0, 1.0]).inverse(), None);
+        assert_eq!(Mat3::scale(Vec2::new(0.0, 1.0)).determinant(), 0.0);
+    }
+
+    #[test]
+    fn mat4_multiply() {
+        let a = Mat3::translation(Vec2::new(1.0, 2.0));
+        let b = Mat3::rotation(0.3)*Mat3::scale(Vec2::new(2.0, 2.0));
+
+        assert_eq!(Mat4::id()*a.to_mat4(), a.to_mat4());
+        assert_eq!(a.to_mat4()*Mat4::id(), a.to_mat4());
+
+        // embedding keeps the product
+        let product = a.to_mat4()*b.to_mat4();
+        let expected = (a*b).to_mat4();
+
+        for (x, y) in product.as_slice().iter().zip(expected.as_slice()) {
+            assert!((x - y).abs() < EPS);
+        }
+
+        // translation goes to the last column as shaders expect
+        assert_eq!(a.to_mat4().get(0, 3), 1.0);
+        assert_eq!(a.to_mat4().get(1, 3), 2.0);
+    }
+
+    #[test]
+    fn aabb_from_points() {
+        assert_eq!(Aabb::from_points(&[]), None);
+
+        let aabb = Aabb::from_points(&[Vec2::new(1.0, -1.0), Vec2::new(-2.0, 3.0), Vec2::new(0.0, 0.0)]).unwrap();
+        assert_eq!(aabb, Aabb::new(Vec2::new(-2.0, -1.0), Vec2::new(1.0, 3.0)));
+
+        // corners are sorted
+        assert_eq!(Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.0)), Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn aabb_contains() {
+        let aabb = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 1.0));
+
+        assert!(aabb.contains(Vec2::new(1.0, 0.5)));
+        // edges and corners are inside
+        assert!(aabb.contains(Vec2::new(0.0, 0.0)));
+        assert!(aabb.contains(Vec2::new(2.0, 1.0)));
+        assert!(aabb.contains(Vec2::new(2.0, 0.5)));
+        assert!(!aabb.contains(Vec2::new(2.0001, 0.5)));
+        assert!(!aabb.contains(Vec2::new(1.0, -0.0001)));
+
+        // degenerate box is a point
+        let point = Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(1.0, 1.0));
+        assert!(point.contains(Vec2::new(1.0, 1.0)));
+        assert!(!point.contains(Vec2::new(1.0, 1.1)));
+    }
+
+    #[test]
+    fn aabb_intersects() {
+        let a = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0));
+
+        assert!(a.intersects(&Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(3.0, 3.0))));
+        // one inside another
+        assert!(a.intersects(&Aabb::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0))));
+        // touching edge and corner count
+        assert!(a.intersects(&Aabb::new(Vec2::new(2.0, 0.0), Vec2::new(3.0, 1.0))));
+        assert!(a.intersects(&Aabb::new(Vec2::new(2.0, 2.0), Vec2::new(3.0, 3.0))));
+        // apart along one axis is enough
+        assert!(!a.intersects(&Aabb::new(Vec2::new(2.1, 0.0), Vec2::new(3.0, 1.0))));
+        assert!(!a.intersects(&Aabb::new(Vec2::new(0.0, -2.0), Vec2::new(1.0, -0.1))));
+    }
+
+    #[test]
+    fn aabb_center_and_size() {
+        let a = Aabb::new(Vec2::new(3.0, 1.0), Vec2::new(-1.0, 2.0));
+
+        assert_eq!(a.center(), Vec2::new(1.0, 1.5));
+        assert_eq!(a.size(), Vec2::new(4.0, 1.0));
+
+        let point = Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(1.0, 1.0));
+        assert_eq!(point.size(), Vec2::zero());
+    }
+
+    #[test]
+    fn aabb_contains_aabb() {
+        let a = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0));
+
+        assert!(a.contains_aabb(&Aabb::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0))));
+        // itself and boxes on the border
+        assert!(a.contains_aabb(&a));
+        assert!(a.contains_aabb(&Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 1.0))));
+        // overlapping is not enough
+        assert!(!a.contains_aabb(&Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(3.0, 1.5))));
+        assert!(!Aabb::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0)).contains_aabb(&a));
+    }
+
+    #[test]
+    fn aabb_intersection_and_union() {
+        let a = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0));
+        let b = Aabb::new(Vec2::new(1.0, -1.0), Vec2::new(3.0, 1.0));
+
+        assert_eq!(a.intersection(&b), Some(Aabb::new(Vec2::new(1.0, 0.0), Vec2::new(2.0, 1.0))));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+        // touching boxes share an edge
+        assert_eq!(
+            a.intersection(&Aabb::new(Vec2::new(2.0, 0.0), Vec2::new(3.0, 2.0))),
+            Some(Aabb::new(Vec2::new(2.0, 0.0), Vec2::new(2.0, 2.0)))
+        );
+        assert_eq!(a.intersection(&Aabb::new(Vec2::new(2.5, 0.0), Vec2::new(3.0, 2.0))), None);
+
+        let union = a.union(&b);
+        assert_eq!(union, Aabb::new(Vec2::new(0.0, -1.0), Vec2::new(3.0, 2.0)));
+        assert!(union.contains_aabb(&a) && union.contains_aabb(&b));
+    }
+
+    #[test]
+    fn mat3_transform_vector_ignores_translation() {
+        let m = Mat3::translation(Vec2::new(5.0, -3.0))*Mat3::rotation(PI / 2.0)*Mat3::scale(Vec2::new(2.0, 1.0));
+        let v = Vec2::new(1.0, 1.0);
+
+        assert_near(m.transform_vector(v), Vec2::new(-1.0, 2.0));
+        assert_near(m.transform_vector(v), m.transform_point(v) - m.transform_point(Vec2::zero()));
+        assert_eq!(Mat3::translation(Vec2::new(5.0, -3.0)).transform_vector(v), v);
+    }
+}
@@ -0,0 +1,166 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::transform::*;
+use crate::math::*;
+use crate::tween::*;
+
+// Default is the root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NodeId(usize);
+
+// What is drawn at the node position
+#[derive(Debug, Clone, PartialEq)]
+pub enum Drawable {
+    Nothing,
+    // index of dynamic shape
+    Figure(usize),
+    // index of static shape
+    Sprite(usize),
+    // text drawn by ui
+    Label(String),
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub transform: Transform,
+    pub props: Props,
+    pub tweens: Tweener,
+    // hides the whole subtree
+    pub visible: bool,
+    pub drawable: Drawable,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>
+}
+
+// Nodes are stored in one vector and refer to each other by index
+// Child transform, layer, alpha and visibility are relative to the parent
+#[derive(Debug)]
+pub struct SceneGraph {
+    nodes: Vec<Node>
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        let root = Node {
+            transform: Transform::id(),
+            props: Props::default(),
+            tweens: Tweener::new(),
+            visible: true,
+            drawable: Drawable::Nothing,
+            parent: None,
+            children: Vec::new()
+        };
+
+        SceneGraph {
+            nodes: vec![root]
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn add(&mut self, parent: NodeId, transform: Transform, drawable: Drawable) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(Node {
+            transform,
+            props: Props::default(),
+            tweens: Tweener::new(),
+            visible: true,
+            drawable,
+            parent: Some(parent),
+            children: Vec::new()
+        });
+
+        self.nodes[parent.0].children.push(id);
+
+        id
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn set_parent(&mut self, id: NodeId, parent: NodeId) {
+        if let Some(old) = self.nodes[id.0].parent {
+            self.nodes[old.0].children.retain(|&child| child != id);
+        }
+
+        self.nodes[id.0].parent = Some(parent);
+        self.nodes[parent.0].children.push(id);
+    }
+
+    // Node matrix with animated props
+    fn local_matrix(&self, id: NodeId) -> Mat3 {
+        let node = &self.nodes[id.0];
+
+        node.transform.matrix() * node.props.transform().matrix()
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> Mat3 {
+        let mut matrix = self.local_matrix(id);
+        let mut current = self.nodes[id.0].parent;
+
+        while let Some(parent) = current {
+            matrix = self.local_matrix(parent) * matrix;
+            current = self.nodes[parent.0].parent;
+        }
+
+        matrix
+    }
+
+    pub fn world_layer(&self, id: NodeId) -> i32 {
+        self.ancestors(id).map(|node| node.transform.layer).sum()
+    }
+
+    pub fn world_alpha(&self, id: NodeId) -> f32 {
+        self.ancestors(id).map(|node| node.props.alpha).product()
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        for node in self.nodes.iter_mut() {
+            node.tweens.update(&mut node.props, dt);
+        }
+    }
+
+    // Visible nodes with something to draw from the bottom layer to the top one
+    // nodes of the same layer keep tree order
+    pub fn draw_list(&self) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut stack = vec![self.root()];
+
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id.0];
+
+            if !node.visible {
+                continue;
+            }
+
+            if node.drawable != Drawable::Nothing {
+                result.push(id);
+            }
+
+            stack.extend(node.children.iter().rev());
+        }
+
+        result.sort_by_key(|&id| self.world_layer(id));
+
+        result
+    }
+
+    // node itself and all its parents up to the root
+    fn ancestors(&self, id: NodeId) -> impl Iterator<Item = &Node> {
+        std::iter::successors(Some(&self.nodes[id.0]), |node| node.parent.map(|parent| &self.nodes[parent.0]))
+    }
+}
+
+impl Default for SceneGraph {
+    fn default() -> Self {
+        SceneGraph::new()
+    }
+}
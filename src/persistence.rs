@@ -0,0 +1,353 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::save::*;
+use crate::game_state::GameState;
+use crate::game_mode::ModeKind;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+// localStorage key of the save
+const SAVE_KEY: &str = "tg_game_demo.save";
+// Previous save to recover from if the current one is broken
+const BACKUP_KEY: &str = "tg_game_demo.save.backup";
+// Broken save is kept here for investigation
+const CORRUPTED_KEY: &str = "tg_game_demo.save.corrupted";
+// Mute flag stored by itself before saves existed
+const LEGACY_MUTED_KEY: &str = "tg_game_demo.muted";
+
+// Key value storage the save lives in
+pub trait Backend {
+    fn read(&self, key: &str) -> Option<String>;
+    fn write(&mut self, key: &str, value: &str);
+    fn remove(&mut self, key: &str);
+}
+
+// Browser localStorage, does nothing when it is disabled
+#[derive(Debug)]
+pub struct LocalStorageBackend {
+    storage: Option<web_sys::Storage>
+}
+
+impl LocalStorageBackend {
+    pub fn new() -> LocalStorageBackend {
+        LocalStorageBackend {
+            storage: web_sys::window().and_then(|window| window.local_storage().ok()?)
+        }
+    }
+}
+
+impl Backend for LocalStorageBackend {
+    fn read(&self, key: &str) -> Option<String> {
+        self.storage.as_ref()?.get_item(key).ok()?
+    }
+
+    fn write(&mut self, key: &str, value: &str) {
+        if let Some(storage) = &self.storage {
+            let _ = storage.set_item(key, value);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(storage) = &self.storage {
+            let _ = storage.remove_item(key);
+        }
+    }
+}
+
+// Keeps everything in memory, stands in for localStorage in tests
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    items: HashMap<String, String>
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+}
+
+#[cfg(test)]
+impl Backend for MemoryBackend {
+    fn read(&self, key: &str) -> Option<String> {
+        self.items.get(key).cloned()
+    }
+
+    fn write(&mut self, key: &str, value: &str) {
+        self.items.insert(key.to_string(), value.to_string());
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.items.remove(key);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub muted: bool,
+    pub accessible: bool,
+    // None keeps the default one
+    pub round_time: Option<f64>,
+    // last picked in the menu
+    pub mode: ModeKind
+}
+
+// Fields missing in the stored json take default values
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub best_score: u64,
+    pub total_games: u64,
+    pub settings: Settings,
+    // names of unlocked content
+    pub unlocks: Vec<String>,
+    // unfinished game to continue after reload
+    pub game: Option<GameState>
+}
+
+// Save data loaded on start and written back on every change
+#[derive(Debug)]
+pub struct Persistence<B: Backend> {
+    backend: B,
+    data: SaveData,
+    // text of the last good save, becomes backup on the next write
+    last_saved: Option<String>,
+    // why the stored save was dropped on load
+    error: Option<SaveError>
+}
+
+impl<B: Backend> Persistence<B> {
+    // Broken save is replaced with the backup or starts from scratch
+    pub fn load(mut backend: B) -> Persistence<B> {
+        let (data, last_saved, error) = match backend.read(SAVE_KEY) {
+            Some(text) => match decode(&text) {
+                Ok(data) => (data, Some(text), None),
+                Err(error) => {
+                    backend.write(CORRUPTED_KEY, &text);
+
+                    let backup = backend.read(BACKUP_KEY).and_then(|text| Some((decode(&text).ok()?, text)));
+
+                    match backup {
+                        Some((data, text)) => (data, Some(text), Some(error)),
+                        None => (SaveData::default(), None, Some(error))
+                    }
+                }
+            },
+            None => (migrate_legacy(&mut backend), None, None)
+        };
+
+        let mut persistence = Persistence {
+            backend,
+            data,
+            last_saved,
+            error
+        };
+
+        // stored in the current version from now on
+        persistence.save();
+
+        persistence
+    }
+
+    pub fn error(&self) -> Option<&SaveError> {
+        self.error.as_ref()
+    }
+
+    pub fn data(&self) -> &SaveData {
+        &self.data
+    }
+
+    // Changes are written by `save`
+    pub fn data_mut(&mut self) -> &mut SaveData {
+        &mut self.data
+    }
+
+    pub fn save(&mut self) {
+        let text = encode(&self.data);
+
+        if let Some(previous) = self.last_saved.replace(text.clone()) {
+            self.backend.write(BACKUP_KEY, &previous);
+        }
+
+        self.backend.write(SAVE_KEY, &text);
+    }
+
+    // true if the score is a new record
+    pub fn submit_score(&mut self, score: u64) -> bool {
+        if score <= self.data.best_score {
+            return false;
+        }
+
+        self.data.best_score = score;
+        self.save();
+
+        true
+    }
+
+    // false if it was unlocked before
+    pub fn unlock(&mut self, name: &str) -> bool {
+        if self.is_unlocked(name) {
+            return false;
+        }
+
+        self.data.unlocks.push(name.to_string());
+        self.save();
+
+        true
+    }
+
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        self.data.unlocks.iter().any(|unlock| unlock == name)
+    }
+}
+
+// Picks up separate keys written by older builds
+fn migrate_legacy<B: Backend>(backend: &mut B) -> SaveData {
+    let mut data = SaveData::default();
+
+    if let Some(muted) = backend.read(LEGACY_MUTED_KEY) {
+        data.settings.muted = muted == "1";
+        backend.remove(LEGACY_MUTED_KEY);
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What the next start of the game reads
+    fn reload(persistence: Persistence<MemoryBackend>) -> Persistence<MemoryBackend> {
+        Persistence::load(persistence.backend)
+    }
+
+    #[test]
+    fn best_score_survives_reload() {
+        let mut persistence = Persistence::load(MemoryBackend::new());
+
+        assert!(persistence.submit_score(120));
+        assert!(!persistence.submit_score(100));
+        assert!(!persistence.submit_score(120));
+
+        let persistence = reload(persistence);
+
+        assert_eq!(persistence.data().best_score, 120);
+        assert_eq!(persistence.error(), None);
+    }
+
+    #[test]
+    fn games_and_settings_survive_reload() {
+        let mut persistence = Persistence::load(MemoryBackend::new());
+
+        let settings = Settings {
+            muted: true,
+            accessible: true,
+            round_time: Some(2.5),
+            mode: ModeKind::TimeAttack
+        };
+
+        let data = persistence.data_mut();
+        data.total_games = 3;
+        data.settings = settings.clone();
+        persistence.save();
+
+        assert!(persistence.unlock("golden"));
+
+        let persistence = reload(persistence);
+
+        assert_eq!(persistence.data().total_games, 3);
+        assert_eq!(persistence.data().settings, settings);
+        assert!(persistence.is_unlocked("golden"));
+    }
+
+    #[test]
+    fn unfinished_game_survives_reload() {
+        let mut persistence = Persistence::load(MemoryBackend::new());
+
+        let game = GameState::new(ModeKind::Endless);
+        persistence.data_mut().game = Some(game.clone());
+        persistence.save();
+
+        assert_eq!(reload(persistence).data().game, Some(game));
+    }
+
+    #[test]
+    fn legacy_mute_flag_is_migrated() {
+        let mut backend = MemoryBackend::new();
+        backend.write(LEGACY_MUTED_KEY, "1");
+
+        let persistence = Persistence::load(backend);
+
+        assert!(persistence.data().settings.muted);
+        assert_eq!(persistence.backend.read(LEGACY_MUTED_KEY), None);
+
+        // read from the save from now on
+        let persistence = reload(persistence);
+
+        assert!(persistence.data().settings.muted);
+        assert_eq!(persistence.error(), None);
+    }
+
+    #[test]
+    fn corrupted_save_is_replaced_with_backup() {
+        let corrupted = include_str!("../fixtures/saves/v5_bad_checksum.json");
+        let backup = include_str!("../fixtures/saves/v4.json");
+
+        let mut backend = MemoryBackend::new();
+        backend.write(SAVE_KEY, corrupted);
+        backend.write(BACKUP_KEY, backup);
+
+        let persistence = Persistence::load(backend);
+
+        assert_eq!(persistence.error(), Some(&SaveError::ChecksumMismatch));
+        assert_eq!(persistence.data(), &decode::<SaveData>(backup).unwrap());
+        assert_eq!(persistence.backend.read(CORRUPTED_KEY).as_deref(), Some(corrupted));
+
+        // the backup is written back as the current save
+        let persistence = reload(persistence);
+
+        assert_eq!(persistence.error(), None);
+        assert_eq!(persistence.data().best_score, 700);
+    }
+
+    #[test]
+    fn broken_save_without_backup_starts_from_scratch() {
+        let broken = include_str!("../fixtures/saves/broken.json");
+
+        let mut backend = MemoryBackend::new();
+        backend.write(SAVE_KEY, broken);
+
+        let persistence = Persistence::load(backend);
+
+        assert!(matches!(persistence.error(), Some(SaveError::Malformed(_))));
+        assert_eq!(persistence.data(), &SaveData::default());
+        assert_eq!(persistence.backend.read(CORRUPTED_KEY).as_deref(), Some(broken));
+    }
+
+    #[test]
+    fn old_save_is_migrated_on_load() {
+        let mut backend = MemoryBackend::new();
+        backend.write(SAVE_KEY, include_str!("../fixtures/saves/v1.json"));
+
+        let persistence = Persistence::load(backend);
+
+        assert_eq!(persistence.error(), None);
+        assert_eq!(persistence.data().best_score, 340);
+        assert!(persistence.data().settings.muted);
+        assert!(persistence.backend.read(SAVE_KEY).unwrap().contains(&format!("\"version\":{}", SAVE_VERSION)));
+    }
+
+    #[test]
+    fn empty_storage_starts_from_scratch() {
+        let persistence = Persistence::load(MemoryBackend::new());
+
+        assert_eq!(persistence.data(), &SaveData::default());
+        assert!(persistence.backend.read(SAVE_KEY).is_some());
+    }
+}
@@ -1,7 +1,9 @@
 #[allow(unused_imports)]
 use crate::log;
 
-#[derive(Debug, Clone, Copy)]
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Point {
     data: [f32; 2]
 }
@@ -13,6 +15,10 @@ impl Point {
         }
     }
 
+    pub fn zero() -> Point {
+        Point::new(0.0, 0.0)
+    }
+
     pub fn from_screen_coords(x: f32, y: f32) -> Point {
         Point {
             data: [2.0*x - 1.0, 1.0 - 2.0*y]
@@ -26,4 +32,101 @@ impl Point {
     pub fn y(&self) -> f32 {
         self.data[1]
     }
-}
\ No newline at end of file
+
+    pub fn dot(&self, other: Point) -> f32 {
+        self.x()*other.x() + self.y()*other.y()
+    }
+
+    // z component of 3d cross product, > 0 when other is counterclockwise from self
+    pub fn cross(&self, other: Point) -> f32 {
+        self.x()*other.y() - self.y()*other.x()
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.dot(*self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(&self, other: Point) -> f32 {
+        (*self - other).length()
+    }
+
+    // zero vector stays zero
+    pub fn normalize(&self) -> Point {
+        let length = self.length();
+
+        if length > 0.0 { *self / length } else { *self }
+    }
+
+    // rotated by 90 degrees counterclockwise
+    pub fn perp(&self) -> Point {
+        Point::new(-self.y(), self.x())
+    }
+
+    pub fn lerp(&self, other: Point, t: f32) -> Point {
+        *self + (other - *self)*t
+    }
+
+    pub fn min(&self, other: Point) -> Point {
+        Point::new(self.x().min(other.x()), self.y().min(other.y()))
+    }
+
+    pub fn max(&self, other: Point) -> Point {
+        Point::new(self.x().max(other.x()), self.y().max(other.y()))
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x() + other.x(), self.y() + other.y())
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x() - other.x(), self.y() - other.y())
+    }
+}
+
+impl Mul<f32> for Point {
+    type Output = Point;
+
+    fn mul(self, k: f32) -> Point {
+        Point::new(self.x()*k, self.y()*k)
+    }
+}
+
+impl Div<f32> for Point {
+    type Output = Point;
+
+    fn div(self, k: f32) -> Point {
+        Point::new(self.x() / k, self.y() / k)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point::new(-self.x(), -self.y())
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Point) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Point {
+    fn sub_assign(&mut self, other: Point) {
+        *self = *self - other;
+    }
+}
@@ -1,44 +1,10 @@
-use web_sys;
-
 use crate::shape::*;
 use crate::texture::Texture;
+use crate::math::Mat4;
 
 #[allow(unused_imports)]
 use crate::log;
 
-#[derive(Debug, Clone, Copy)]
-pub struct TransformInfo(pub f32, pub f32);
-
-impl TransformInfo {
-    // column order
-    pub fn scale_matrix(&self) -> [f32; 16] {
-        [
-            self.0, 0.0, 0.0, 0.0,
-            0.0, self.1, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.0, 0.0, 0.0, 1.0
-        ]
-    }
-
-    pub fn translation_matrix(&self) -> [f32; 16] {
-        [
-            1.0, 0.0, 0.0, 0.0,
-            0.0, 1.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            self.0, self.1, 1.0, 1.0
-        ]
-    }
-
-    pub fn id() -> [f32; 16] {
-        [
-            1.0, 0.0, 0.0, 0.0,
-            0.0, 1.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.0, 0.0, 0.0, 1.0
-        ]
-    }
-}
-
 #[derive(Debug, Clone, Copy)]
 struct ShapeDescriptor {
     pub offset: usize,
@@ -155,11 +121,11 @@ impl IndicesRender {
         }
     }
 
-    pub fn write_uniform(&self, data: &[f32], uniform: &str) {
+    pub fn write_uniform(&self, data: &Mat4, uniform: &str) {
         let location: web_sys::WebGlUniformLocation =
             self.context.get_uniform_location(&self.program, uniform).expect("Failed to get uniform location");
 
-        self.context.uniform_matrix4fv_with_f32_array(Some(&location), false, data);
+        self.context.uniform_matrix4fv_with_f32_array(Some(&location), false, data.as_slice());
     }
 
     pub fn enable_texture(&self, texture: &str) {
@@ -169,9 +135,25 @@ impl IndicesRender {
         self.context.uniform1i(Some(&location), 0);
     }
 
-    pub fn draw(&self, shape_idx: usize, texture: &Texture) {
+    pub fn write_float(&self, var: f32, uniform: &str) {
+        let location =
+            self.context.get_uniform_location(&self.program, uniform);
+
+        self.context.uniform1f(location.as_ref(), var);
+    }
+
+    pub fn write_vec4(&self, data: &[f32; 4], uniform: &str) {
+        let location =
+            self.context.get_uniform_location(&self.program, uniform);
+
+        self.context.uniform4fv_with_f32_array(location.as_ref(), data);
+    }
+
+    pub fn bind_texture(&self, texture: &Texture) {
         self.context.bind_texture(web_sys::WebGl2RenderingContext::TEXTURE_2D, texture.as_ref());
+    }
 
+    pub fn draw(&self, shape_idx: usize) {
         let desc: &ShapeDescriptor = &self.descriptors[shape_idx];
 
         self.context.draw_elements_with_i32(
@@ -298,17 +280,26 @@ impl FeedbackRender {
         self.context.uniform1f(location.as_ref(), var);
     }
 
-    pub fn write_uniform(&self, data: &[f32], uniform: &str) {
+    pub fn write_uniform(&self, data: &Mat4, uniform: &str) {
         let location: web_sys::WebGlUniformLocation =
             self.context.get_uniform_location(&self.program, uniform).expect("Failed to get uniform location");
 
-        self.context.uniform_matrix4fv_with_f32_array(Some(&location), false, data);
+        self.context.uniform_matrix4fv_with_f32_array(Some(&location), false, data.as_slice());
     }
 
-    pub fn draw(&self, shape_idx: usize, texture: &Texture) {
-        self.context.use_program(Some(&self.program));
+    pub fn write_vec4(&self, data: &[f32; 4], uniform: &str) {
+        let location =
+            self.context.get_uniform_location(&self.program, uniform);
+
+        self.context.uniform4fv_with_f32_array(location.as_ref(), data);
+    }
 
+    pub fn bind_texture(&self, texture: &Texture) {
         self.context.bind_texture(web_sys::WebGl2RenderingContext::TEXTURE_2D, texture.as_ref());
+    }
+
+    pub fn draw(&self, shape_idx: usize) {
+        self.context.use_program(Some(&self.program));
 
         self.context.begin_transform_feedback(web_sys::WebGl2RenderingContext::TRIANGLES);
 
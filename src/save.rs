@@ -0,0 +1,220 @@
+#[allow(unused_imports)]
+use crate::log;
+
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+// Version written by this build
+pub const SAVE_VERSION: u32 = 5;
+
+// Migration from version `n` to `n + 1` is MIGRATIONS[n - 1]
+const MIGRATIONS: &[fn(Value) -> Value] = &[
+    v1_to_v2,
+    v2_to_v3,
+    v3_to_v4,
+    v4_to_v5,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveError {
+    // not json or not the expected shape
+    Malformed(String),
+    // written by a newer build
+    UnknownVersion(u32),
+    ChecksumMismatch,
+}
+
+// Stored form: data with its version and crc32 of canonical json
+// version 1 had no envelope, data fields were at the top level
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    checksum: u32,
+    data: Value
+}
+
+pub fn encode<T: Serialize>(data: &T) -> String {
+    let data = serde_json::to_value(data).expect("Failed to serialize save");
+
+    let envelope = Envelope {
+        version: SAVE_VERSION,
+        checksum: checksum(&data),
+        data
+    };
+
+    serde_json::to_string(&envelope).expect("Failed to serialize save")
+}
+
+// Checks integrity and migrates older versions up to the current one
+pub fn decode<T: DeserializeOwned>(text: &str) -> Result<T, SaveError> {
+    let value: Value = serde_json::from_str(text).map_err(|e| SaveError::Malformed(e.to_string()))?;
+
+    let (version, data) = match value.get("data") {
+        Some(_) => {
+            let envelope: Envelope = serde_json::from_value(value).map_err(|e| SaveError::Malformed(e.to_string()))?;
+
+            // checksum of newer versions may be computed differently
+            if envelope.version > SAVE_VERSION {
+                return Err(SaveError::UnknownVersion(envelope.version));
+            }
+
+            if checksum(&envelope.data) != envelope.checksum {
+                return Err(SaveError::ChecksumMismatch);
+            }
+
+            (envelope.version, envelope.data)
+        },
+        None => (1, value)
+    };
+
+    if version == 0 || version > SAVE_VERSION {
+        return Err(SaveError::UnknownVersion(version));
+    }
+
+    let data = MIGRATIONS[(version - 1) as usize..].iter().fold(data, |data, migrate| migrate(data));
+
+    serde_json::from_value(data).map_err(|e| SaveError::Malformed(e.to_string()))
+}
+
+// Keys of serde_json maps are sorted, so the same data always gives the same text
+fn checksum(data: &Value) -> u32 {
+    crc32fast::hash(data.to_string().as_bytes())
+}
+
+// Version moved into envelope, unfinished game is saved too
+fn v1_to_v2(mut data: Value) -> Value {
+    if let Some(fields) = data.as_object_mut() {
+        fields.remove("version");
+        fields.insert("game".to_string(), json!(null));
+    }
+
+    data
+}
+
+// Games got modes, older unfinished games had no end so they go on as zen
+fn v2_to_v3(mut data: Value) -> Value {
+    if let Some(game) = data.get_mut("game").and_then(Value::as_object_mut) {
+        game.insert("mode".to_string(), json!("zen"));
+        game.insert("lives".to_string(), json!(0));
+        game.insert("elapsed".to_string(), json!(0.0));
+    }
+
+    data
+}
+
+// Hits in a row are counted for combos
+fn v3_to_v4(mut data: Value) -> Value {
+    if let Some(game) = data.get_mut("game").and_then(Value::as_object_mut) {
+        game.insert("combo".to_string(), json!(0));
+        game.insert("best_combo".to_string(), json!(0));
+    }
+
+    data
+}
+
+// Holes have their own timers, the round one is gone
+fn v4_to_v5(mut data: Value) -> Value {
+    if let Some(game) = data.get_mut("game").and_then(Value::as_object_mut) {
+        game.remove("timer");
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::SaveData;
+
+    // Saves written by older builds and broken ones
+    const V1: &str = include_str!("../fixtures/saves/v1.json");
+    const V2: &str = include_str!("../fixtures/saves/v2.json");
+    const V3: &str = include_str!("../fixtures/saves/v3.json");
+    const V4: &str = include_str!("../fixtures/saves/v4.json");
+    const V5_BAD_CHECKSUM: &str = include_str!("../fixtures/saves/v5_bad_checksum.json");
+    const BROKEN: &str = include_str!("../fixtures/saves/broken.json");
+
+    // Save data as this build writes it
+    fn current(data: Value) -> SaveData {
+        serde_json::from_value(data).expect("Failed to parse expected save")
+    }
+
+    #[test]
+    fn migrates_v1() {
+        let expected = current(json!({
+            "best_score": 340,
+            "total_games": 12,
+            "settings": { "muted": true, "accessible": false, "round_time": null, "mode": "classic" },
+            "unlocks": [],
+            "game": null
+        }));
+
+        assert_eq!(decode::<SaveData>(V1), Ok(expected));
+    }
+
+    #[test]
+    fn migrates_v2() {
+        let expected = current(json!({
+            "best_score": 520,
+            "total_games": 20,
+            "settings": { "muted": false, "accessible": true, "round_time": 2.5, "mode": "classic" },
+            "unlocks": ["golden"],
+            "game": { "mode": "zen", "score": 150, "round_time": 1.5, "lives": 0, "elapsed": 0.0, "combo": 0, "best_combo": 0 }
+        }));
+
+        assert_eq!(decode::<SaveData>(V2), Ok(expected));
+    }
+
+    #[test]
+    fn migrates_v3() {
+        let expected = current(json!({
+            "best_score": 610,
+            "total_games": 31,
+            "settings": { "muted": false, "accessible": false, "round_time": null, "mode": "time_attack" },
+            "unlocks": ["golden", "combo_10"],
+            "game": { "mode": "classic", "score": 90, "round_time": 2.0, "lives": 2, "elapsed": 14.5, "combo": 0, "best_combo": 0 }
+        }));
+
+        assert_eq!(decode::<SaveData>(V3), Ok(expected));
+    }
+
+    #[test]
+    fn migrates_v4() {
+        let expected = current(json!({
+            "best_score": 700,
+            "total_games": 44,
+            "settings": { "muted": true, "accessible": false, "round_time": 3.0, "mode": "endless" },
+            "unlocks": ["golden"],
+            "game": { "mode": "endless", "score": 230, "round_time": 1.5, "lives": 1, "elapsed": 40.0, "combo": 4, "best_combo": 9 }
+        }));
+
+        assert_eq!(decode::<SaveData>(V4), Ok(expected));
+    }
+
+    #[test]
+    fn migrated_save_is_written_in_current_version() {
+        let data: SaveData = decode(V2).expect("Failed to decode save");
+        let text = encode(&data);
+
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap()["version"], json!(SAVE_VERSION));
+        assert_eq!(decode::<SaveData>(&text), Ok(data));
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        assert_eq!(decode::<SaveData>(V5_BAD_CHECKSUM), Err(SaveError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn rejects_broken_json() {
+        assert!(matches!(decode::<SaveData>(BROKEN), Err(SaveError::Malformed(_))));
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let text = json!({ "version": SAVE_VERSION + 1, "checksum": 0, "data": {} }).to_string();
+
+        assert_eq!(decode::<SaveData>(&text), Err(SaveError::UnknownVersion(SAVE_VERSION + 1)));
+    }
+}
@@ -5,29 +5,79 @@ use crate::log;
 use crate::shape::*;
 use crate::render::*;
 use crate::shader::*;
-use crate::texture::*;
-use crate::rand::*;
+use crate::atlas::*;
+use crate::animation::*;
+use crate::tween::*;
 use crate::point::*;
+use crate::math::*;
+use crate::transform::*;
+use crate::node::*;
+use crate::collision::*;
+use crate::special::*;
 
-fn get_transforms(u: f32, d: f32, l: f32, r: f32, count_x: u32, count_y: u32) -> Vec<TransformInfo> {
-    let dx = (r - l) / (2*count_x) as f32;
-    let dy = (u - d) / (2*count_y) as f32;
+// Size of a figure relative to the screen
+const FIGURE_SCALE: f32 = 0.25;
 
-    let mut result: Vec<TransformInfo> = Vec::new();
+// Holes are placed in grid
+pub const GRID_COLUMNS: u32 = 1;
+pub const GRID_ROWS: u32 = 3;
+
+// Selection ring is drawn over everything
+const CURSOR_LAYER: i32 = 100;
+const CURSOR_SIZE: f32 = 0.3;
+
+// Grid of holes, lower rows are drawn on top of upper ones
+fn get_transforms(u: f32, d: f32, l: f32, r: f32, count_x: u32, count_y: u32) -> Vec<Transform> {
+    // cell size
+    let step = Vec2::new((r - l) / count_x as f32, (u - d) / count_y as f32);
+
+    let mut result: Vec<Transform> = Vec::new();
 
     for i in 0..count_x {
         for j in 0..count_y {
-            result.push(TransformInfo(l + dx + (i as f32)*2.0*dx, d + dy + (j as f32)*2.0*dy));
+            let transform = Transform {
+                translation: Vec2::new(l + (i as f32 + 0.5)*step.x(), d + (j as f32 + 0.5)*step.y()),
+                scale: Vec2::new(FIGURE_SCALE, FIGURE_SCALE),
+                layer: 10*(count_y - j) as i32,
+                ..Transform::id()
+            };
+
+            result.push(transform);
         }
     }
 
     result
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum State {
-    Initial,
-    Done,
+const SPARK_SIZE: u32 = 64;
+
+// Score popup lines of a hole and distance between them in hole coords
+const POPUP_LINES: usize = 5;
+const POPUP_LINE: f32 = 0.4;
+
+// Pixels less opaque than this are not hit
+const ALPHA_THRESHOLD: u8 = 128;
+
+// Points checked against the alpha mask along a slash
+const SLASH_SAMPLES: u32 = 16;
+
+// White ring fading to the edges
+fn spark_texture(size: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((4*size*size) as usize);
+
+    for y in 0..size {
+        for x in 0..size {
+            let dx = 2.0*(x as f32 + 0.5) / size as f32 - 1.0;
+            let dy = 2.0*(y as f32 + 0.5) / size as f32 - 1.0;
+
+            let r = (dx*dx + dy*dy).sqrt();
+            let alpha = (1.0 - ((r - 0.75).abs() / 0.25)).clamp(0.0, 1.0);
+
+            data.extend_from_slice(&[255, 255, 255, (255.0*alpha) as u8]);
+        }
+    }
+
+    data
 }
 
 #[derive(Debug)]
@@ -35,12 +85,27 @@ pub struct Scene {
     feedback_render: FeedbackRender,
     indices_render: IndicesRender,
     dynamic_shapes: Vec<Shape>,
+    animations: Vec<AnimationPlayer>,
     static_shapes: Vec<Shape>,
-    transforms: Vec<TransformInfo>,
-    transform_indices: Vec<usize>,
-    state: State,
-    time: f64,
-    textures: Vec<Texture>
+    graph: SceneGraph,
+    holes: Vec<Hole>,
+    // figure node of every dynamic shape
+    figures: Vec<NodeId>,
+    // hole of every figure, None while it is off the field
+    hole_indices: Vec<Option<usize>>,
+    // selection ring for keyboard players
+    cursor: NodeId,
+    // visible part of every figure from 0 to 1
+    rises: Vec<f32>,
+    atlas: Atlas
+}
+
+// Hole node owns the figure which currently sits in it and hit feedback
+#[derive(Debug, Clone, Copy)]
+struct Hole {
+    node: NodeId,
+    effect: NodeId,
+    popups: [NodeId; POPUP_LINES]
 }
 
 impl Scene {
@@ -52,8 +117,11 @@ impl Scene {
             .dyn_into::<web_sys::WebGl2RenderingContext>()
             .expect("Failed to get WebGl2RenderingContext");
 
-        gl.enable(web_sys::WebGl2RenderingContext::DEPTH_TEST);
-        gl.depth_func(web_sys::WebGl2RenderingContext::LEQUAL);
+        // draw order is defined by layers
+        gl.disable(web_sys::WebGl2RenderingContext::DEPTH_TEST);
+
+        gl.enable(web_sys::WebGl2RenderingContext::BLEND);
+        gl.blend_func(web_sys::WebGl2RenderingContext::SRC_ALPHA, web_sys::WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
 
         gl.clear(web_sys::WebGl2RenderingContext::COLOR_BUFFER_BIT | web_sys::WebGl2RenderingContext::DEPTH_BUFFER_BIT);
         gl.clear_color(1.0, 1.0, 1.0, 1.0);
@@ -65,16 +133,16 @@ impl Scene {
             255, 0, 255, 255
         ];
 
-        let default_texture = from_rgba_data(&gl, &default_raw_texture, 2, 2, web_sys::WebGl2RenderingContext::RGBA);
+        let mut atlas_builder = AtlasBuilder::with_embedded_sprites();
+        atlas_builder.add_rgba("default", &default_raw_texture, 2, 2);
+        atlas_builder.add_rgba("spark", &spark_texture(SPARK_SIZE), SPARK_SIZE, SPARK_SIZE);
 
-        let fig_raw_texture = include_bytes!("textures/princess.png");
-        let fig_texture = from_png_data(&gl, fig_raw_texture, web_sys::WebGl2RenderingContext::RGBA);
-
-        let back_raw_texture = include_bytes!("textures/brick.png");
-        let back_texture = from_png_data(&gl, back_raw_texture, web_sys::WebGl2RenderingContext::RGBA);
+        for special in SPECIALS {
+            let (data, size) = special.texture();
+            atlas_builder.add_rgba(special.sprite(), &data, size, size);
+        }
 
-        let other_fig_raw_texture = include_bytes!("textures/horn_girl.png");
-        let other_fig_texture = from_png_data(&gl, other_fig_raw_texture, web_sys::WebGl2RenderingContext::RGBA);
+        let atlas = Atlas::new(&gl, atlas_builder.pack());
 
         // feedback render
         let feedback_render = FeedbackRender::new(&gl);
@@ -88,12 +156,6 @@ impl Scene {
 
         feedback_render.enable_texture("tex");
 
-        feedback_render.write_uniform(&TransformInfo(0.25, 0.25).scale_matrix(), "scale");
-
-        let transforms = get_transforms(0.8, -1.0, -1.0, 1.0, 1, 3);
-
-        let transform_indices: Vec<usize> = std::ops::Range{start: 0, end: transforms.len()}.into_iter().collect();
-
         // indices render
         let indices_render = IndicesRender::new(&gl);
 
@@ -105,62 +167,218 @@ impl Scene {
         indices_render.setup_render();
 
         indices_render.enable_texture("tex");
-        indices_render.write_uniform(&TransformInfo::id(), "translation");
-        indices_render.write_uniform(&TransformInfo::id(), "scale");
 
-        Scene {
+        let mut scene = Scene {
             feedback_render,
             indices_render,
             dynamic_shapes: Vec::new(),
+            animations: Vec::new(),
             static_shapes: Vec::new(),
-            transforms,
-            transform_indices,
-            state: State::Initial,
-            time: 0.0,
-            textures: vec![default_texture, fig_texture, back_texture, other_fig_texture]
+            graph: SceneGraph::new(),
+            holes: Vec::new(),
+            figures: Vec::new(),
+            hole_indices: Vec::new(),
+            cursor: NodeId::default(),
+            rises: Vec::new(),
+            atlas
+        };
+
+        for transform in get_transforms(0.8, -1.0, -1.0, 1.0, GRID_COLUMNS, GRID_ROWS) {
+            scene.add_hole(transform);
         }
+
+        scene.add_cursor();
+
+        scene
+    }
+
+    fn add_cursor(&mut self) {
+        let root = self.graph.root();
+
+        let shape = Shape::circle(1.0, 32, "spark");
+
+        let transform = Transform {
+            scale: Vec2::new(CURSOR_SIZE, CURSOR_SIZE),
+            layer: CURSOR_LAYER,
+            ..Transform::id()
+        };
+
+        self.cursor = self.graph.add(root, transform, Drawable::Sprite(self.static_shapes.len()));
+        self.static_shapes.push(shape.clone());
+        self.indices_render.add(&shape);
+
+        let node = self.graph.node_mut(self.cursor);
+        node.visible = false;
+        node.props.alpha = 0.6;
     }
 
-    pub fn permutate_transforms(&mut self) {
-        for i in 0..self.dynamic_shapes.len() {
-            let j = rand_in_range(i as u64, self.transform_indices.len() as u64) as usize;
+    // Hides cursor on None
+    pub fn set_cursor(&mut self, position: Option<Point>) {
+        let node = self.graph.node_mut(self.cursor);
+
+        node.visible = position.is_some();
+        node.transform.translation = position.unwrap_or(node.transform.translation);
+    }
 
-            self.transform_indices.swap(i, j);
+    // Holes are numbered row by row from the top left one
+    pub fn hole_by_number(&self, number: usize) -> Option<usize> {
+        let (columns, rows) = (GRID_COLUMNS as usize, GRID_ROWS as usize);
+
+        if number >= columns*rows {
+            return None;
         }
+
+        let (row, column) = (number / columns, number % columns);
+
+        // get_transforms goes column by column from the bottom
+        Some(column*rows + rows - 1 - row)
     }
 
-    pub fn render(&mut self, dt: f64) {
-        // render static shapes
-        self.indices_render.setup_render();
+    pub fn hole_number(&self, hole: usize) -> usize {
+        let rows = GRID_ROWS as usize;
+        let (column, row) = (hole / rows, rows - 1 - hole % rows);
+
+        row*GRID_COLUMNS as usize + column
+    }
+
+    pub fn hole_count(&self) -> usize {
+        self.holes.len()
+    }
+
+    pub fn hole_position(&self, hole: usize) -> Point {
+        self.graph.world_matrix(self.holes[hole].node).transform_point(Point::zero())
+    }
+
+    // Figure sitting in the hole
+    pub fn figure_in_hole(&self, hole: usize) -> Option<usize> {
+        (0..self.figures.len()).find(|&i| self.hole_indices[i] == Some(hole))
+    }
+
+    // None if the figure is off the field
+    pub fn hole_of_figure(&self, idx: usize) -> Option<usize> {
+        self.hole_indices[idx]
+    }
+
+    fn add_hole(&mut self, transform: Transform) {
+        let root = self.graph.root();
+
+        let node = self.graph.add(root, transform, Drawable::Nothing);
+
+        let effect_shape = Shape::circle(1.0, 32, "spark");
+        let effect = self.graph.add(node, Transform { layer: 1, ..Transform::id() }, Drawable::Sprite(self.static_shapes.len()));
+        self.static_shapes.push(effect_shape.clone());
+        self.indices_render.add(&effect_shape);
 
-        for i in 0..self.static_shapes.len() {
-            let texture_id = self.static_shapes[i].texture_id;
+        let popups = std::array::from_fn(|i| {
+            let transform = Transform {
+                translation: Vec2::new(0.0, -(i as f32)*POPUP_LINE),
+                layer: 2,
+                ..Transform::id()
+            };
 
-            self.indices_render.draw(i, &self.textures[texture_id]);
+            self.graph.add(node, transform, Drawable::Label(String::new()))
+        });
+
+        self.graph.node_mut(effect).visible = false;
+
+        for popup in popups {
+            self.graph.node_mut(popup).visible = false;
         }
 
-        // render dynamic shapes
-        self.feedback_render.setup_render();
-        self.feedback_render.write_float(self.time as f32, "t");
+        self.holes.push(Hole { node, effect, popups });
+    }
 
-        for i in 0..self.dynamic_shapes.len() {
-            let transform_idx = self.transform_indices[i];
-            let texture_id = self.dynamic_shapes[i].texture_id;
+    // None takes the figure off the field
+    pub fn place_figure(&mut self, idx: usize, hole: Option<usize>) {
+        self.hole_indices[idx] = hole;
 
-            self.feedback_render.write_uniform(&self.transforms[transform_idx].translation_matrix(), "translation");
+        let parent = match hole {
+            Some(hole) => self.holes[hole].node,
+            None => self.graph.root()
+        };
 
-            self.feedback_render.draw(i, &self.textures[texture_id]);
+        self.graph.set_parent(self.figures[idx], parent);
+        self.graph.node_mut(self.figures[idx]).visible = hole.is_some();
+    }
+
+    // Figure rises from the bottom of its hole, only the upper part is visible
+    pub fn set_rise(&mut self, idx: usize, rise: f64) {
+        self.rises[idx] = rise.clamp(0.0, 1.0) as f32;
+    }
 
-            self.dynamic_shapes[i].update_vertices(self.feedback_render.read_vertices(i));
+    pub fn render(&mut self, dt: f64) {
+        self.graph.update(dt);
+
+        // hit feedback lives until its animation is over
+        for hole in self.holes.iter() {
+            for &id in std::iter::once(&hole.effect).chain(hole.popups.iter()) {
+                let node = self.graph.node_mut(id);
+                node.visible = node.visible && node.tweens.is_active();
+            }
+        }
+
+        for animation in self.animations.iter_mut() {
+            animation.advance(dt);
         }
 
-        self.time += dt;
+        self.feedback_render.setup_render();
+        self.feedback_render.bind_texture(self.atlas.texture());
 
-        if self.time >= 1.0 {
-            self.state = State::Done;
+        self.indices_render.setup_render();
+        self.indices_render.bind_texture(self.atlas.texture());
+
+        // programs are switched only when the next node needs another one
+        let mut feedback_active = false;
+
+        for id in self.graph.draw_list() {
+            let model = self.graph.world_matrix(id).to_mat4();
+            let alpha = self.graph.world_alpha(id);
+
+            match self.graph.node(id).drawable {
+                Drawable::Figure(i) => {
+                    if !feedback_active {
+                        self.feedback_render.setup_render();
+                        feedback_active = true;
+                    }
+
+                    let frame = self.animations[i].frame();
+                    let region = self.atlas.frame(&frame.sprite, frame.index);
+
+                    self.feedback_render.write_uniform(&model, "model");
+                    self.feedback_render.write_float(alpha, "alpha");
+                    self.feedback_render.write_float(self.rises[i], "t");
+                    self.feedback_render.write_vec4(&region.as_array(), "uvRect");
+
+                    self.feedback_render.draw(i);
+
+                    self.dynamic_shapes[i].update_vertices(self.feedback_render.read_vertices(i));
+                },
+                Drawable::Sprite(i) => {
+                    if feedback_active {
+                        self.indices_render.setup_render();
+                        feedback_active = false;
+                    }
+
+                    let region = self.atlas.region(&self.static_shapes[i].sprite);
+
+                    self.indices_render.write_uniform(&model, "model");
+                    self.indices_render.write_float(alpha, "alpha");
+                    self.indices_render.write_vec4(&region.as_array(), "uvRect");
+                    self.indices_render.draw(i);
+                },
+                Drawable::Label(_) | Drawable::Nothing => {}
+            }
         }
+    }
 
-        self.time = self.time.clamp(0.0, 1.0);
+    // Visible labels: position in webgl coords, text and alpha
+    pub fn labels(&self) -> Vec<(Point, String, f32)> {
+        self.graph.draw_list().into_iter()
+            .filter_map(|id| match &self.graph.node(id).drawable {
+                Drawable::Label(text) => Some((self.graph.world_matrix(id).transform_point(Point::zero()), text.clone(), self.graph.world_alpha(id))),
+                _ => None
+            })
+            .collect()
     }
 
     pub fn update_renders(&mut self) {
@@ -171,26 +389,133 @@ impl Scene {
         self.indices_render.write_vertices("vertexPosition");
     }
 
-    pub fn add_dynamic_shape(&mut self, shape: &Shape) {
+    // Figure waits off the field until it is placed into a hole
+    pub fn add_dynamic_shape(&mut self, shape: &Shape) -> usize {
+        let idx = self.dynamic_shapes.len();
+
+        self.figures.push(self.graph.add(self.graph.root(), shape.transform, Drawable::Figure(idx)));
+        self.hole_indices.push(None);
+        self.rises.push(0.0);
+        self.place_figure(idx, None);
+
         self.dynamic_shapes.push(shape.clone());
+        self.animations.push(AnimationPlayer::new(AnimationSet::from_atlas(&self.atlas, &shape.sprite)));
         self.feedback_render.add(shape);
+
+        idx
     }
 
     pub fn add_static_shape(&mut self, shape: &Shape) {
+        let root = self.graph.root();
+
+        self.graph.add(root, shape.transform, Drawable::Sprite(self.static_shapes.len()));
         self.static_shapes.push(shape.clone());
         self.indices_render.add(shape);
     }
 
+    pub fn dynamic_shapes_count(&self) -> usize {
+        self.dynamic_shapes.len()
+    }
+
+    // Placement in the hole and animated props, they come from the world every frame
+    pub fn set_figure_state(&mut self, idx: usize, transform: Transform, props: Props) {
+        let node = self.graph.node_mut(self.figures[idx]);
+
+        node.transform = transform;
+        node.props = props;
+    }
+
+    // Effect is shown in the hole of the figure `idx`
+    pub fn hit_effect(&mut self, idx: usize, sequence: Sequence) {
+        let Some(hole) = self.hole_indices[idx] else {
+            return;
+        };
+
+        Self::restart(self.graph.node_mut(self.holes[hole].effect), sequence);
+    }
+
+    // One line under another, `sequence` gets the line number
+    // lines stay hidden until their sequence shows them
+    pub fn score_popups(&mut self, idx: usize, lines: &[String], sequence: impl Fn(usize) -> Sequence) {
+        let Some(hole) = self.hole_indices[idx] else {
+            return;
+        };
+
+        let popups = self.holes[hole].popups;
+
+        for (i, (&popup, text)) in popups.iter().zip(lines).enumerate() {
+            let node = self.graph.node_mut(popup);
+            node.drawable = Drawable::Label(text.clone());
+
+            Self::restart(node, sequence(i));
+            node.props.alpha = 0.0;
+        }
+
+        for &popup in popups.iter().skip(lines.len()) {
+            self.graph.node_mut(popup).visible = false;
+        }
+    }
+
+    fn restart(node: &mut Node, sequence: Sequence) {
+        node.visible = true;
+        node.props = Props::default();
+        node.tweens.clear();
+        node.tweens.add(sequence);
+    }
+
+    pub fn figure_layer(&self, idx: usize) -> i32 {
+        self.graph.world_layer(self.figures[idx])
+    }
+
+    // Outline bounds of figures on the field
+    pub fn figure_bounds(&self, idx: usize) -> Option<Aabb> {
+        self.hole_indices[idx].and(self.dynamic_shapes[idx].bounds())
+    }
+
+    // Figures off the field are never hit
     pub fn is_dynamic_hit(&self, idx: usize, point: Point) -> bool {
-        self.dynamic_shapes[idx].contains(point)
+        self.hole_indices[idx].is_some() && self.dynamic_shapes[idx].contains(point) && self.is_opaque(idx, point)
+    }
+
+    // Segment crosses outline and passes over some opaque pixel
+    pub fn is_dynamic_slashed(&self, idx: usize, segment: &Segment) -> bool {
+        if self.hole_indices[idx].is_none() || !segment_hits_shape(segment, &self.dynamic_shapes[idx]) {
+            return false;
+        }
+
+        (0..=SLASH_SAMPLES)
+            .map(|i| segment.a.lerp(segment.b, i as f32 / SLASH_SAMPLES as f32))
+            .any(|p| self.is_dynamic_hit(idx, p))
     }
 
-    pub fn state(&self) -> State {
-        self.state
+    // Checks sprite pixel under the point, sprites without mask are opaque everywhere
+    fn is_opaque(&self, idx: usize, point: Point) -> bool {
+        let frame = self.animations[idx].frame();
+
+        let Some(mask) = self.atlas.mask(&frame.sprite, frame.index) else {
+            return true;
+        };
+
+        let Some(inverse) = self.graph.world_matrix(self.figures[idx]).inverse() else {
+            return false;
+        };
+
+        // same mapping as in feedback::VERTEX_SHADER
+        // figure rises from the bottom and only the upper part of the sprite is visible
+        let t = self.rises[idx];
+        let pos = inverse.transform_point(point);
+
+        if pos.y() > 2.0*t - 1.0 {
+            return false;
+        }
+
+        let u = (pos.x() + 1.0)*0.5;
+        let v = t - (pos.y() + 1.0)*0.5*t;
+
+        mask.alpha_at(u, v) >= ALPHA_THRESHOLD
     }
 
-    pub fn reset(&mut self) {
-        self.time = 0.0;
-        self.state = State::Initial;
+    pub fn play(&mut self, idx: usize, clip: Clip) {
+        self.animations[idx].play(clip);
     }
 }
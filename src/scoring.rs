@@ -0,0 +1,130 @@
+#[allow(unused_imports)]
+use crate::log;
+
+// Points of every hit before the combo multiplier
+const BASE_POINTS: u64 = 10;
+// Bonus of an instant hit, goes down to 0 by the end of the round
+const MAX_REACTION_BONUS: u64 = 10;
+// Hit within this time after the figure starts rising is perfect, rising takes 0.4
+pub const PERFECT_WINDOW: f64 = 0.5;
+const PERFECT_BONUS: u64 = 15;
+// Multiplier grows by one every COMBO_STEP hits in a row
+const COMBO_STEP: u32 = 5;
+const MAX_MULTIPLIER: u64 = 5;
+
+// Points of one hit by parts, so UI can show where they came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitScore {
+    pub base: u64,
+    pub reaction: u64,
+    pub perfect: u64,
+    // e.g. golden figure
+    pub bonus: u64,
+    pub multiplier: u64,
+    // hits in a row including this one
+    pub combo: u32
+}
+
+impl HitScore {
+    // `reaction_time` is seconds since the figure started rising, rounds last `round_time`
+    pub fn new(combo: u32, reaction_time: f64, round_time: f64, bonus: u64) -> HitScore {
+        let left = (1.0 - reaction_time / round_time).clamp(0.0, 1.0);
+
+        HitScore {
+            base: BASE_POINTS,
+            reaction: (left*MAX_REACTION_BONUS as f64).round() as u64,
+            perfect: if reaction_time <= PERFECT_WINDOW { PERFECT_BONUS } else { 0 },
+            bonus,
+            multiplier: (1 + (combo / COMBO_STEP) as u64).min(MAX_MULTIPLIER),
+            combo
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        (self.base + self.reaction + self.perfect + self.bonus)*self.multiplier
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.perfect > 0
+    }
+
+    // Total first, then bonuses which made it
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("+{}", self.total())];
+
+        if self.reaction > 0 {
+            lines.push(format!("Fast +{}", self.reaction));
+        }
+
+        if self.perfect > 0 {
+            lines.push(format!("Perfect +{}", self.perfect));
+        }
+
+        if self.bonus > 0 {
+            lines.push(format!("Bonus +{}", self.bonus));
+        }
+
+        if self.multiplier > 1 {
+            lines.push(format!("Combo {} x{}", self.combo, self.multiplier));
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUND_TIME: f64 = 1.0;
+
+    #[test]
+    fn multiplier_grows_every_combo_step() {
+        let multiplier = |combo| HitScore::new(combo, ROUND_TIME, ROUND_TIME, 0).multiplier;
+
+        assert_eq!(multiplier(1), 1);
+        assert_eq!(multiplier(COMBO_STEP - 1), 1);
+        assert_eq!(multiplier(COMBO_STEP), 2);
+        assert_eq!(multiplier(2*COMBO_STEP + 1), 3);
+        assert_eq!(multiplier(100*COMBO_STEP), MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn reaction_bonus_goes_down_by_the_end_of_the_round() {
+        let reaction = |time| HitScore::new(1, time, ROUND_TIME, 0).reaction;
+
+        assert_eq!(reaction(0.0), MAX_REACTION_BONUS);
+        assert_eq!(reaction(ROUND_TIME / 2.0), MAX_REACTION_BONUS / 2);
+        assert_eq!(reaction(ROUND_TIME), 0);
+        assert_eq!(reaction(2.0*ROUND_TIME), 0);
+
+        // faster is never worse
+        assert!((0..10).all(|i| reaction(i as f64 * 0.1) >= reaction((i + 1) as f64 * 0.1)));
+    }
+
+    #[test]
+    fn hits_in_the_window_are_perfect() {
+        assert!(HitScore::new(1, 0.0, ROUND_TIME, 0).is_perfect());
+        assert!(HitScore::new(1, PERFECT_WINDOW, ROUND_TIME, 0).is_perfect());
+        assert!(!HitScore::new(1, PERFECT_WINDOW + 0.01, ROUND_TIME, 0).is_perfect());
+        assert_eq!(HitScore::new(1, 0.0, ROUND_TIME, 0).perfect, PERFECT_BONUS);
+    }
+
+    #[test]
+    fn total_is_multiplied_sum_of_parts() {
+        let hit = HitScore::new(COMBO_STEP, 0.0, ROUND_TIME, 50);
+        assert_eq!(hit.total(), (BASE_POINTS + MAX_REACTION_BONUS + PERFECT_BONUS + 50)*2);
+
+        let late = HitScore::new(1, ROUND_TIME, ROUND_TIME, 0);
+        assert_eq!(late.total(), BASE_POINTS);
+        assert_eq!(late.lines(), vec![format!("+{}", BASE_POINTS)]);
+
+        assert_eq!(hit.lines(), vec![
+            format!("+{}", hit.total()),
+            format!("Fast +{}", MAX_REACTION_BONUS),
+            format!("Perfect +{}", PERFECT_BONUS),
+            String::from("Bonus +50"),
+            format!("Combo {} x2", COMBO_STEP)
+        ]);
+    }
+}
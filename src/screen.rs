@@ -0,0 +1,177 @@
+#[allow(unused_imports)]
+use crate::log;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    // waits for assets decoded in background
+    Loading,
+    // mode is picked here
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+    // overlays, they go back to the screen they were opened from
+    Leaderboard,
+    Settings,
+}
+
+impl Screen {
+    pub fn is_overlay(self) -> bool {
+        matches!(self, Screen::Leaderboard | Screen::Settings)
+    }
+
+    // Screens this one may go to, overlays also go back
+    fn next(self) -> &'static [Screen] {
+        match self {
+            Screen::Loading => &[Screen::Title, Screen::Paused],
+            Screen::Title => &[Screen::Playing, Screen::Leaderboard, Screen::Settings],
+            // overlays are opened over the paused game
+            Screen::Playing => &[Screen::Paused, Screen::GameOver],
+            Screen::Paused => &[Screen::Playing, Screen::Title, Screen::Leaderboard, Screen::Settings],
+            Screen::GameOver => &[Screen::Playing, Screen::Title, Screen::Leaderboard, Screen::Settings],
+            Screen::Leaderboard => &[Screen::Settings],
+            Screen::Settings => &[Screen::Leaderboard]
+        }
+    }
+}
+
+// Current screen and the one under overlays
+#[derive(Debug)]
+pub struct ScreenMachine {
+    current: Screen,
+    // None unless an overlay is shown
+    back: Option<Screen>,
+    // seconds on the current screen
+    time: f64
+}
+
+impl ScreenMachine {
+    pub fn new() -> ScreenMachine {
+        ScreenMachine {
+            current: Screen::Loading,
+            back: None,
+            time: 0.0
+        }
+    }
+
+    pub fn current(&self) -> Screen {
+        self.current
+    }
+
+    // Screen the overlay goes back to
+    pub fn back(&self) -> Option<Screen> {
+        self.back
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.time += dt;
+    }
+
+    // false if there is no such transition, the screen stays then
+    pub fn go(&mut self, to: Screen) -> bool {
+        if !self.current.next().contains(&to) && Some(to) != self.back {
+            return false;
+        }
+
+        if !to.is_overlay() {
+            self.back = None;
+        } else if !self.current.is_overlay() {
+            self.back = Some(self.current);
+        }
+
+        self.current = to;
+        self.time = 0.0;
+
+        true
+    }
+}
+
+impl Default for ScreenMachine {
+    fn default() -> Self {
+        ScreenMachine::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine(path: &[Screen]) -> ScreenMachine {
+        let mut screens = ScreenMachine::new();
+
+        for &screen in path {
+            assert!(screens.go(screen), "no way to {:?}", screen);
+        }
+
+        screens
+    }
+
+    #[test]
+    fn invalid_transitions_keep_the_screen() {
+        let mut screens = machine(&[]);
+        assert!(!screens.go(Screen::Playing));
+        assert!(!screens.go(Screen::Loading));
+        assert_eq!(screens.current(), Screen::Loading);
+
+        let mut screens = machine(&[Screen::Title, Screen::Playing]);
+        screens.update(1.0);
+        // overlays are opened over the pause only
+        assert!(!screens.go(Screen::Leaderboard));
+        assert!(!screens.go(Screen::Title));
+        assert_eq!(screens.current(), Screen::Playing);
+        assert_eq!(screens.time(), 1.0);
+    }
+
+    #[test]
+    fn overlay_to_overlay_keeps_back() {
+        let mut screens = machine(&[Screen::Title, Screen::Playing, Screen::Paused, Screen::Leaderboard]);
+        assert_eq!(screens.back(), Some(Screen::Paused));
+
+        assert!(screens.go(Screen::Settings));
+        assert_eq!(screens.back(), Some(Screen::Paused));
+
+        // back is allowed from any overlay, the other screens are not
+        assert!(!screens.go(Screen::Playing));
+        assert!(screens.go(Screen::Paused));
+        assert_eq!(screens.back(), None);
+    }
+
+    #[test]
+    fn overlay_goes_back_only_where_it_came_from() {
+        let mut screens = machine(&[Screen::Title, Screen::Settings]);
+
+        assert_eq!(screens.back(), Some(Screen::Title));
+        assert!(!screens.go(Screen::GameOver));
+        assert!(screens.go(Screen::Title));
+        assert_eq!(screens.current(), Screen::Title);
+    }
+
+    #[test]
+    fn saved_game_resumes_from_loading() {
+        let mut screens = machine(&[Screen::Paused]);
+        assert_eq!(screens.back(), None);
+
+        assert!(screens.go(Screen::Playing));
+        assert!(screens.go(Screen::GameOver));
+    }
+
+    #[test]
+    fn time_starts_over_on_every_screen() {
+        let mut screens = machine(&[Screen::Title]);
+        screens.update(0.25);
+        screens.update(0.25);
+        assert_eq!(screens.time(), 0.5);
+
+        screens.go(Screen::Playing);
+        assert_eq!(screens.time(), 0.0);
+
+        // failed transition does not reset it
+        screens.update(0.5);
+        screens.go(Screen::Title);
+        assert_eq!(screens.time(), 0.5);
+    }
+}
@@ -3,14 +3,15 @@ pub mod background {
 
     in vec2 vertexPosition;
 
-    uniform mat4 scale;
-    uniform mat4 translation;
+    uniform mat4 model;
+    uniform vec4 uvRect;
 
     out vec2 uv;
 
     void main() {
-        gl_Position = translation*scale*vec4(vertexPosition, 1.0, 1.0);
+        gl_Position = model*vec4(vertexPosition, 0.0, 1.0);
         uv = vec2(1, 1) - (vertexPosition.xy + vec2(1, 1)) * 0.5;
+        uv = uvRect.xy + uv*uvRect.zw;
     }"#;
 
     pub const FRAGMENT_SHADER: &str = r#"#version 300 es
@@ -20,11 +21,13 @@ pub mod background {
     in vec2 uv;
 
     uniform sampler2D tex;
+    uniform float alpha;
 
     out vec4 fragColor;
 
     void main() {
         fragColor = texture(tex, uv);
+        fragColor.a *= alpha;
     }"#;
 }
 
@@ -33,9 +36,9 @@ pub mod feedback {
 
     in vec2 vertexPosition;
 
-    uniform mat4 scale;
-    uniform mat4 translation;
+    uniform mat4 model;
     uniform float t;
+    uniform vec4 uvRect;
 
     out vec4 vertexPos;
     out vec2 vertOut;
@@ -44,11 +47,12 @@ pub mod feedback {
     void main() {
         vec2 pos = vertexPosition;
         pos.y = pos.y*t + t - 1.0;
-        gl_Position = translation*scale*vec4(pos, 0.0, 1.0);
+        gl_Position = model*vec4(pos, 0.0, 1.0);
         vertexPos = gl_Position;
         vertOut = gl_Position.xy;
         uv = (pos.xy + vec2(1, 1)) * 0.5;
         uv.y = -uv.y*t + t;
+        uv = uvRect.xy + uv*uvRect.zw;
     }"#;
 
     pub const FRAGMENT_SHADER: &str = r#"#version 300 es
@@ -59,11 +63,13 @@ pub mod feedback {
     in vec2 uv;
 
     uniform sampler2D tex;
+    uniform float alpha;
 
     out vec4 fragColor;
 
     void main() {
         fragColor = texture(tex, uv);
+        fragColor.a *= alpha;
     }"#;
 }
 
@@ -1,21 +1,103 @@
 use crate::point::Point;
+use crate::math::Aabb;
+use crate::transform::Transform;
 
 #[allow(unused_imports)]
 use crate::log;
 
+use std::f32::consts::PI;
+
 #[derive(Debug, Clone, Default)]
 pub struct Shape {
     pub vertices: Vec<Point>,
     pub indices: Vec<u32>,
-    pub texture_id: usize
+    pub sprite: String,
+    // placement inside the parent, e.g. hole
+    pub transform: Transform
 }
 
 impl Shape {
-    pub fn square(texture_id: usize) -> Shape {
+    pub fn square(sprite: &str) -> Shape {
         Shape {
             vertices: vec![Point::new(-1.0, -1.0), Point::new(-1.0, 1.0), Point::new(1.0, 1.0), Point::new(1.0, -1.0)],
             indices: vec![0, 1, 2, 2, 3, 0],
-            texture_id
+            sprite: String::from(sprite),
+            transform: Transform::id()
+        }
+    }
+
+    // Axis aligned ellipse with radiuses rx and ry
+    pub fn ellipse(rx: f32, ry: f32, segments: u32, sprite: &str) -> Shape {
+        let vertices = (0..segments)
+            .map(|i| {
+                let angle = 2.0*PI*(i as f32) / (segments as f32);
+                Point::new(rx*angle.cos(), ry*angle.sin())
+            })
+            .collect();
+
+        Shape::convex(vertices, sprite)
+    }
+
+    pub fn circle(radius: f32, segments: u32, sprite: &str) -> Shape {
+        Shape::ellipse(radius, radius, segments, sprite)
+    }
+
+    // First vertex points up
+    #[allow(unused)]
+    pub fn regular_polygon(sides: u32, radius: f32, sprite: &str) -> Shape {
+        let vertices = (0..sides)
+            .map(|i| {
+                let angle = PI / 2.0 + 2.0*PI*(i as f32) / (sides as f32);
+                Point::new(radius*angle.cos(), radius*angle.sin())
+            })
+            .collect();
+
+        Shape::convex(vertices, sprite)
+    }
+
+    // Rectangle centered at origin, every corner is an arc of `segments` pieces
+    #[allow(unused)]
+    pub fn rounded_rectangle(width: f32, height: f32, radius: f32, segments: u32, sprite: &str) -> Shape {
+        let radius = radius.min(width / 2.0).min(height / 2.0);
+
+        let (hw, hh) = (width / 2.0 - radius, height / 2.0 - radius);
+
+        // corner centers counterclockwise starting from the top right one
+        let corners = [(hw, hh), (-hw, hh), (-hw, -hh), (hw, -hh)];
+
+        let mut vertices = Vec::new();
+
+        for (k, (cx, cy)) in corners.iter().enumerate() {
+            for i in 0..=segments {
+                let angle = PI / 2.0 * (k as f32 + i as f32 / segments.max(1) as f32);
+                vertices.push(Point::new(cx + radius*angle.cos(), cy + radius*angle.sin()));
+            }
+        }
+
+        Shape::convex(vertices, sprite)
+    }
+
+    // Any simple polygon, vertices go along the outline in any direction
+    pub fn polygon(outline: &[Point], sprite: &str) -> Shape {
+        Shape {
+            vertices: outline.to_vec(),
+            indices: triangulate(outline),
+            sprite: String::from(sprite),
+            transform: Transform::id()
+        }
+    }
+
+    // Triangle fan over the outline, valid only for convex shapes
+    fn convex(vertices: Vec<Point>, sprite: &str) -> Shape {
+        let indices = (1..vertices.len().saturating_sub(1) as u32)
+            .flat_map(|i| [0, i, i + 1])
+            .collect();
+
+        Shape {
+            vertices,
+            indices,
+            sprite: String::from(sprite),
+            transform: Transform::id()
         }
     }
 
@@ -26,9 +108,17 @@ impl Shape {
         }
     }
 
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.vertices)
+    }
+
     // https://ics.uci.edu/~eppstein/161/960307.html
     // https://dl.acm.org/doi/pdf/10.1145/368637.368653
     pub fn contains(&self, point: Point) -> bool {
+        if !self.bounds().is_some_and(|bounds| bounds.contains(point)) {
+            return false;
+        }
+
         let (x, y) = (point.x(), point.y());
 
         let mut hit = true;
@@ -48,3 +138,177 @@ impl Shape {
         !hit
     }
 }
+
+fn signed_area(outline: &[Point]) -> f32 {
+    let mut area = 0.0;
+
+    for i in 0..outline.len() {
+        area += outline[i].cross(outline[(i + 1) % outline.len()]);
+    }
+
+    area / 2.0
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f32 {
+    (a - o).cross(b - o)
+}
+
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+// Ear clipping
+// https://www.geometrictools.com/Documentation/TriangulationByEarClipping.pdf
+fn triangulate(outline: &[Point]) -> Vec<u32> {
+    let mut remaining: Vec<u32> = (0..outline.len() as u32).collect();
+
+    // ears are looked for in counterclockwise order
+    if signed_area(outline) < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut indices = Vec::new();
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (pa, pb, pc) = (outline[a as usize], outline[b as usize], outline[c as usize]);
+
+            cross(pa, pb, pc) > 0.0 && remaining.iter()
+                .filter(|&&j| j != a && j != b && j != c)
+                .all(|&j| !in_triangle(outline[j as usize], pa, pb, pc))
+        });
+
+        // degenerate outline, e.g. self intersecting: the rest is closed with a fan
+        let Some(i) = ear else {
+            break;
+        };
+
+        indices.extend_from_slice(&[remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+
+    for i in 1..remaining.len().saturating_sub(1) {
+        indices.extend_from_slice(&[remaining[0], remaining[i], remaining[i + 1]]);
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(outline: &[(f32, f32)]) -> Vec<Point> {
+        outline.iter().map(|&(x, y)| Point::new(x, y)).collect()
+    }
+
+    // Sum of triangle areas, every triangle must be counterclockwise
+    fn triangles_area(outline: &[Point], indices: &[u32]) -> f32 {
+        indices.chunks_exact(3)
+            .map(|t| {
+                let area = cross(outline[t[0] as usize], outline[t[1] as usize], outline[t[2] as usize]) / 2.0;
+                assert!(area >= 0.0, "triangle {:?} is clockwise", t);
+                area
+            })
+            .sum()
+    }
+
+    fn assert_covers(outline: &[Point]) {
+        let indices = triangulate(outline);
+
+        assert_eq!(indices.len(), 3*(outline.len() - 2));
+        assert!(indices.iter().all(|&i| (i as usize) < outline.len()));
+        assert!((triangles_area(outline, &indices) - signed_area(outline).abs()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn triangulates_convex() {
+        assert_covers(&points(&[(0.0, 0.0), (2.0, 0.0), (3.0, 1.0), (2.0, 2.0), (0.0, 2.0)]));
+    }
+
+    #[test]
+    fn triangulates_clockwise() {
+        assert_covers(&points(&[(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 0.0)]));
+    }
+
+    #[test]
+    fn triangulates_concave() {
+        // arrow with the notch at (1, 1)
+        let outline = points(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (1.0, 3.0)]);
+        assert_covers(&outline);
+
+        // no triangle goes through the notch
+        let shape = Shape::polygon(&outline, "");
+        assert!(!shape.contains(Point::new(1.0, 0.5)));
+        assert!(shape.contains(Point::new(1.0, 2.0)));
+    }
+
+    #[test]
+    fn triangulates_collinear_vertices() {
+        assert_covers(&points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0)]));
+    }
+
+    #[test]
+    fn triangulates_character_outline() {
+        let outline = points(&[
+            (-0.25, 0.37), (0.21, 0.37), (0.29, 0.23), (0.43, 0.16), (0.59, 0.01), (0.70, -0.12),
+            (0.58, -0.26), (0.33, -0.40), (0.27, -0.55), (0.13, -0.64), (-0.13, -0.64), (-0.29, -0.55),
+            (-0.35, -0.40), (-0.62, -0.26), (-0.68, -0.12), (-0.60, 0.01), (-0.47, 0.16), (-0.31, 0.23)
+        ]);
+
+        assert_covers(&outline);
+    }
+
+    // Fan of the shape and ear clipping of its outline cover the same area
+    fn assert_area(shape: &Shape, area: f32) {
+        assert_eq!(shape.indices.len(), 3*(shape.vertices.len() - 2));
+        assert!((triangles_area(&shape.vertices, &shape.indices) - area).abs() < 1e-4);
+
+        assert_covers(&shape.vertices);
+        assert!((triangles_area(&shape.vertices, &triangulate(&shape.vertices)) - area).abs() < 1e-4);
+    }
+
+    #[test]
+    fn triangulates_regular_polygon() {
+        for sides in [3, 5, 6, 12] {
+            let shape = Shape::regular_polygon(sides, 2.0, "");
+            assert_eq!(shape.vertices.len(), sides as usize);
+
+            // n triangles of two radiuses with the angle 2π/n between them
+            let n = sides as f32;
+            assert_area(&shape, n / 2.0 * 4.0 * (2.0*PI / n).sin());
+        }
+
+        let triangle = Shape::regular_polygon(3, 1.0, "");
+        assert!((triangle.vertices[0].y() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn triangulates_rounded_rectangle() {
+        let (width, height, radius, segments) = (4.0, 2.0, 0.5, 8);
+        let shape = Shape::rounded_rectangle(width, height, radius, segments, "");
+
+        assert_eq!(shape.vertices.len(), 4*(segments as usize + 1));
+
+        // rectangle without corner squares plus four fans of `segments` triangles
+        let fan = segments as f32 * radius*radius / 2.0 * (PI / 2.0 / segments as f32).sin();
+        assert_area(&shape, width*height - 4.0*radius*radius + 4.0*fan);
+
+        // close to the exact rounded corners
+        let exact = width*height - (4.0 - PI)*radius*radius;
+        assert!((triangles_area(&shape.vertices, &shape.indices) - exact).abs() < 0.01);
+    }
+
+    #[test]
+    fn rounded_rectangle_radius_is_clamped() {
+        // radius bigger than half the height makes a stadium
+        let shape = Shape::rounded_rectangle(4.0, 2.0, 5.0, 16, "");
+        let bounds = shape.bounds().expect("Failed to get bounds");
+
+        assert!((bounds.max.x() - 2.0).abs() < 1e-5 && (bounds.max.y() - 1.0).abs() < 1e-5);
+        assert!((bounds.min.x() + 2.0).abs() < 1e-5 && (bounds.min.y() + 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,386 @@
+#[allow(unused_imports)]
+use crate::log;
+
+// Seconds a figure takes to rise and to sink
+const RISE_TIME: f64 = 0.4;
+const SINK_TIME: f64 = 0.25;
+// Empty hole waits a random time in this range before the next figure
+const MIN_DELAY: f64 = 0.3;
+const MAX_DELAY: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Hidden,
+    Rising,
+    Up,
+    Sinking,
+}
+
+// What happened to holes during the update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnEvent {
+    // figure starts rising in the hole
+    Spawned { hole: usize, figure: usize },
+    // up time is over and the figure was not hit
+    Escaped { hole: usize, figure: usize },
+    // figure is down and leaves the hole
+    Hidden { hole: usize, figure: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    phase: Phase,
+    // seconds in the current phase
+    time: f64,
+    figure: Option<usize>,
+    // how long the figure stays up
+    up_time: f64,
+    // how long hidden hole stays empty
+    delay: f64,
+    // rise at which sinking started
+    sink_from: f64
+}
+
+// Lifecycle of every hole: hidden -> rising -> up -> sinking -> hidden
+// and which figure comes next, figures are picked by their weights
+#[derive(Debug)]
+pub struct Spawner {
+    slots: Vec<Slot>,
+    // of every figure, figures with 0 never rise
+    weights: Vec<u32>,
+    // new figures rise only while active
+    active: bool,
+    // number in [0; n)
+    random: fn(u64) -> u64
+}
+
+impl Spawner {
+    pub fn new(holes: usize, weights: Vec<u32>, random: fn(u64) -> u64) -> Spawner {
+        let mut spawner = Spawner {
+            slots: Vec::new(),
+            weights,
+            active: true,
+            random
+        };
+
+        spawner.slots = (0..holes)
+            .map(|_| Slot { phase: Phase::Hidden, time: 0.0, figure: None, up_time: 0.0, delay: spawner.roll_delay(), sink_from: 0.0 })
+            .collect();
+
+        spawner
+    }
+
+    pub fn set_weight(&mut self, figure: usize, weight: u32) {
+        self.weights[figure] = weight;
+    }
+
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    // Figures rise for `up_time` seconds, it is taken when they start rising
+    pub fn update(&mut self, dt: f64, up_time: f64) -> Vec<SpawnEvent> {
+        let mut events = Vec::new();
+
+        for hole in 0..self.slots.len() {
+            self.slots[hole].time += dt;
+
+            let slot = &self.slots[hole];
+
+            match (slot.phase, slot.figure) {
+                (Phase::Hidden, _) if self.active && slot.time >= slot.delay => {
+                    match self.pick() {
+                        Some(figure) => {
+                            let slot = &mut self.slots[hole];
+
+                            slot.figure = Some(figure);
+                            slot.up_time = up_time;
+                            slot.phase = Phase::Rising;
+                            slot.time = 0.0;
+
+                            events.push(SpawnEvent::Spawned { hole, figure });
+                        },
+                        // every figure is out, try again later
+                        None => self.hide(hole)
+                    }
+                },
+                (Phase::Rising, _) if slot.time >= RISE_TIME => {
+                    let slot = &mut self.slots[hole];
+
+                    slot.phase = Phase::Up;
+                    slot.time = 0.0;
+                },
+                (Phase::Up, Some(figure)) if slot.time >= slot.up_time => {
+                    self.sink(hole);
+
+                    events.push(SpawnEvent::Escaped { hole, figure });
+                },
+                (Phase::Sinking, Some(figure)) if slot.time >= SINK_TIME => {
+                    self.hide(hole);
+
+                    events.push(SpawnEvent::Hidden { hole, figure });
+                },
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    // Hit figure goes down right away
+    pub fn knock(&mut self, hole: usize) {
+        if matches!(self.slots[hole].phase, Phase::Rising | Phase::Up) {
+            self.sink(hole);
+        }
+    }
+
+    // Everything goes down, e.g. on game over
+    pub fn knock_all(&mut self) {
+        for hole in 0..self.slots.len() {
+            self.knock(hole);
+        }
+    }
+
+    pub fn figure(&self, hole: usize) -> Option<usize> {
+        self.slots[hole].figure
+    }
+
+    // Figure can be hit while it is rising or up
+    pub fn is_hittable(&self, hole: usize) -> bool {
+        matches!(self.slots[hole].phase, Phase::Rising | Phase::Up)
+    }
+
+    // Seconds since the figure started rising, hits are graded by it
+    pub fn reaction_time(&self, hole: usize) -> f64 {
+        let slot = &self.slots[hole];
+
+        match slot.phase {
+            Phase::Rising => slot.time,
+            Phase::Up => RISE_TIME + slot.time,
+            _ => 0.0
+        }
+    }
+
+    // Visible part of the figure from 0 to 1
+    pub fn rise(&self, hole: usize) -> f64 {
+        let slot = &self.slots[hole];
+
+        match slot.phase {
+            Phase::Hidden => 0.0,
+            Phase::Rising => (slot.time / RISE_TIME).min(1.0),
+            Phase::Up => 1.0,
+            Phase::Sinking => slot.sink_from*(1.0 - slot.time / SINK_TIME).max(0.0)
+        }
+    }
+
+    fn sink(&mut self, hole: usize) {
+        let sink_from = self.rise(hole);
+        let slot = &mut self.slots[hole];
+
+        slot.sink_from = sink_from;
+        slot.phase = Phase::Sinking;
+        slot.time = 0.0;
+    }
+
+    fn hide(&mut self, hole: usize) {
+        let delay = self.roll_delay();
+        let slot = &mut self.slots[hole];
+
+        slot.phase = Phase::Hidden;
+        slot.figure = None;
+        slot.time = 0.0;
+        slot.delay = delay;
+    }
+
+    fn roll_delay(&self) -> f64 {
+        MIN_DELAY + (MAX_DELAY - MIN_DELAY)*(self.random)(1000) as f64 / 1000.0
+    }
+
+    // Random free figure by weight
+    fn pick(&self) -> Option<usize> {
+        let free: Vec<(usize, u32)> = self.weights.iter().copied().enumerate()
+            .filter(|&(figure, weight)| weight > 0 && self.slots.iter().all(|slot| slot.figure != Some(figure)))
+            .collect();
+
+        let total: u64 = free.iter().map(|&(_, weight)| weight as u64).sum();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = (self.random)(total);
+
+        for (figure, weight) in free {
+            if roll < weight as u64 {
+                return Some(figure);
+            }
+
+            roll -= weight as u64;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP_TIME: f64 = 1.0;
+
+    // shortest delay and the first free figure
+    fn first(_: u64) -> u64 {
+        0
+    }
+
+    // longest delay and the last free figure
+    fn last(n: u64) -> u64 {
+        n - 1
+    }
+
+    fn middle(n: u64) -> u64 {
+        n / 2
+    }
+
+    fn phases(spawner: &Spawner) -> Vec<Phase> {
+        spawner.slots.iter().map(|slot| slot.phase).collect()
+    }
+
+    #[test]
+    fn hole_goes_through_every_phase() {
+        let mut spawner = Spawner::new(1, vec![1], first);
+
+        assert_eq!(spawner.update(MIN_DELAY, UP_TIME), vec![SpawnEvent::Spawned { hole: 0, figure: 0 }]);
+        assert_eq!(phases(&spawner), vec![Phase::Rising]);
+
+        assert_eq!(spawner.update(RISE_TIME, UP_TIME), vec![]);
+        assert_eq!(phases(&spawner), vec![Phase::Up]);
+
+        assert_eq!(spawner.update(UP_TIME, UP_TIME), vec![SpawnEvent::Escaped { hole: 0, figure: 0 }]);
+        assert_eq!(phases(&spawner), vec![Phase::Sinking]);
+
+        assert_eq!(spawner.update(SINK_TIME, UP_TIME), vec![SpawnEvent::Hidden { hole: 0, figure: 0 }]);
+        assert_eq!(phases(&spawner), vec![Phase::Hidden]);
+        assert_eq!(spawner.figure(0), None);
+    }
+
+    #[test]
+    fn reaction_is_counted_from_the_rise() {
+        let mut spawner = Spawner::new(1, vec![1], first);
+
+        spawner.update(MIN_DELAY, UP_TIME);
+        assert_eq!(spawner.reaction_time(0), 0.0);
+
+        spawner.update(RISE_TIME / 2.0, UP_TIME);
+        assert_eq!(spawner.reaction_time(0), RISE_TIME / 2.0);
+
+        spawner.update(RISE_TIME / 2.0, UP_TIME);
+        spawner.update(0.25, UP_TIME);
+        assert_eq!(phases(&spawner), vec![Phase::Up]);
+        assert_eq!(spawner.reaction_time(0), RISE_TIME + 0.25);
+    }
+
+    #[test]
+    fn inactive_spawner_keeps_holes_empty() {
+        let mut spawner = Spawner::new(2, vec![1, 1], first);
+        spawner.set_active(false);
+
+        assert_eq!(spawner.update(MAX_DELAY, UP_TIME), vec![]);
+        assert_eq!(phases(&spawner), vec![Phase::Hidden, Phase::Hidden]);
+    }
+
+    #[test]
+    fn knocked_figures_do_not_escape() {
+        let mut spawner = Spawner::new(2, vec![1, 1], first);
+
+        spawner.update(MIN_DELAY, UP_TIME);
+        spawner.update(RISE_TIME, UP_TIME);
+        spawner.knock(0);
+
+        assert_eq!(spawner.update(UP_TIME, UP_TIME), vec![
+            SpawnEvent::Hidden { hole: 0, figure: 0 },
+            SpawnEvent::Escaped { hole: 1, figure: 1 }
+        ]);
+
+        assert_eq!(spawner.update(SINK_TIME, UP_TIME), vec![SpawnEvent::Hidden { hole: 1, figure: 1 }]);
+    }
+
+    #[test]
+    fn knock_all_escapes_nothing() {
+        let mut spawner = Spawner::new(3, vec![1, 1, 1], first);
+
+        spawner.update(MIN_DELAY, UP_TIME);
+        spawner.knock_all();
+        spawner.set_active(false);
+
+        let events = spawner.update(UP_TIME, UP_TIME);
+
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|event| matches!(event, SpawnEvent::Hidden { .. })));
+    }
+
+    #[test]
+    fn knock_while_rising_sinks_from_there() {
+        let mut spawner = Spawner::new(1, vec![1], first);
+
+        spawner.update(MIN_DELAY, UP_TIME);
+        spawner.update(RISE_TIME / 2.0, UP_TIME);
+
+        assert!(spawner.is_hittable(0));
+        assert_eq!(spawner.rise(0), 0.5);
+
+        spawner.knock(0);
+
+        assert!(!spawner.is_hittable(0));
+        assert_eq!(spawner.rise(0), 0.5);
+
+        spawner.update(SINK_TIME / 2.0, UP_TIME);
+
+        assert_eq!(spawner.rise(0), 0.25);
+    }
+
+    #[test]
+    fn knock_does_nothing_to_sinking_figure() {
+        let mut spawner = Spawner::new(1, vec![1], first);
+
+        spawner.update(MIN_DELAY, UP_TIME);
+        spawner.update(RISE_TIME, UP_TIME);
+        spawner.knock(0);
+        spawner.update(SINK_TIME / 2.0, UP_TIME);
+
+        spawner.knock(0);
+
+        assert_eq!(spawner.rise(0), 0.5);
+    }
+
+    #[test]
+    fn picks_only_free_figures_with_weight() {
+        for random in [first, middle, last] {
+            let mut spawner = Spawner::new(4, vec![0, 3, 1, 0, 2], random);
+
+            for _ in 0..200 {
+                for event in spawner.update(0.1, UP_TIME) {
+                    if let SpawnEvent::Spawned { figure, .. } = event {
+                        assert!(spawner.weights[figure] > 0);
+                    }
+                }
+
+                let figures: Vec<usize> = spawner.slots.iter().filter_map(|slot| slot.figure).collect();
+
+                // every figure is in one hole at most
+                assert!(figures.iter().enumerate().all(|(i, figure)| !figures[i + 1..].contains(figure)), "{figures:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn hole_waits_when_every_figure_is_out() {
+        let mut spawner = Spawner::new(3, vec![1, 0, 1], first);
+
+        let events = spawner.update(MIN_DELAY, UP_TIME);
+
+        assert_eq!(events, vec![SpawnEvent::Spawned { hole: 0, figure: 0 }, SpawnEvent::Spawned { hole: 1, figure: 2 }]);
+        assert_eq!(phases(&spawner), vec![Phase::Rising, Phase::Rising, Phase::Hidden]);
+        assert_eq!(spawner.figure(2), None);
+    }
+}
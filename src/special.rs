@@ -0,0 +1,195 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::util::query_value;
+use crate::ecs::TargetKind;
+
+// Seconds a clock adds to the game clock
+pub const CLOCK_TIME: f64 = 5.0;
+// Freeze slows figures down
+pub const FREEZE_TIME: f64 = 4.0;
+pub const FREEZE_SCALE: f64 = 0.4;
+// Points of golden figure before the combo multiplier
+pub const GOLDEN_BONUS: u64 = 30;
+
+// Side of generated textures
+const TEXTURE_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Special {
+    // target with bonus points
+    Golden,
+    // target which adds time
+    Clock,
+    // must not be hit
+    Bomb,
+    // target which slows the game down for a while
+    Freeze,
+}
+
+pub const SPECIALS: [Special; 4] = [Special::Golden, Special::Clock, Special::Bomb, Special::Freeze];
+
+impl Special {
+    pub fn sprite(self) -> &'static str {
+        match self {
+            Special::Golden => "golden",
+            Special::Clock => "clock",
+            Special::Bomb => "bomb",
+            Special::Freeze => "freeze"
+        }
+    }
+
+    // Everything but bomb must be hit
+    pub fn is_target(self) -> bool {
+        self != Special::Bomb
+    }
+
+    fn default_weight(self) -> u32 {
+        match self {
+            Special::Golden => 2,
+            Special::Clock => 2,
+            Special::Bomb => 2,
+            Special::Freeze => 1
+        }
+    }
+
+    // Round figure drawn in code, there are no sprites for them yet
+    pub fn texture(self) -> (Vec<u8>, u32) {
+        let size = TEXTURE_SIZE;
+        let mut data = Vec::with_capacity((4*size*size) as usize);
+
+        for y in 0..size {
+            for x in 0..size {
+                // y goes up
+                let dx = 2.0*(x as f32 + 0.5) / size as f32 - 1.0;
+                let dy = 1.0 - 2.0*(y as f32 + 0.5) / size as f32;
+
+                data.extend_from_slice(&self.pixel(dx, dy));
+            }
+        }
+
+        (data, size)
+    }
+
+    fn pixel(self, x: f32, y: f32) -> [u8; 4] {
+        const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+        let r = (x*x + y*y).sqrt();
+
+        // distance from the point to the segment from the center to (ex, ey)
+        let hand = |ex: f32, ey: f32| {
+            let t = ((x*ex + y*ey) / (ex*ex + ey*ey)).clamp(0.0, 1.0);
+            ((x - t*ex).powi(2) + (y - t*ey).powi(2)).sqrt()
+        };
+
+        match self {
+            Special::Golden => match r {
+                r if r > 0.9 => CLEAR,
+                r if r > 0.75 => [200, 140, 20, 255],
+                _ if hand(-0.3, 0.3) < 0.12 => [255, 250, 210, 255],
+                _ => [255, 200, 40, 255]
+            },
+            Special::Clock => match r {
+                r if r > 0.9 => CLEAR,
+                r if r > 0.78 => [60, 60, 70, 255],
+                _ if hand(0.0, 0.6) < 0.06 || hand(0.4, 0.0) < 0.08 => [30, 30, 40, 255],
+                _ => [250, 250, 245, 255]
+            },
+            Special::Bomb => match r {
+                _ if (x - 0.45).abs() < 0.07 && y > 0.55 && y < 0.95 => [230, 120, 20, 255],
+                r if r > 0.75 => CLEAR,
+                _ if hand(-0.25, 0.25) < 0.1 => [110, 110, 120, 255],
+                _ => [35, 35, 40, 255]
+            },
+            Special::Freeze => match r {
+                r if r > 0.9 => CLEAR,
+                _ if [(0.0, 0.7), (0.6, 0.35), (0.6, -0.35)].iter().any(|&(ex, ey)| hand(ex, ey) < 0.07 || hand(-ex, -ey) < 0.07) => [255, 255, 255, 255],
+                _ => [120, 190, 240, 255]
+            }
+        }
+    }
+}
+
+// Spawn weights of figures, the spawner picks free ones by them
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnTable {
+    target: u32,
+    // of every decoy
+    decoy: u32,
+    specials: Vec<(Special, u32)>
+}
+
+const TARGET_WEIGHT: u32 = 10;
+const DECOY_WEIGHT: u32 = 4;
+
+impl SpawnTable {
+    // Weights are changed with url parameters, e.g. `?bomb_weight=0`
+    pub fn from_query(query: &str) -> SpawnTable {
+        let weight = |name: &str, default: u32| query_value(query, &format!("{}_weight", name)).and_then(|w| w.parse().ok()).unwrap_or(default);
+
+        SpawnTable {
+            target: weight("target", TARGET_WEIGHT),
+            decoy: weight("decoy", DECOY_WEIGHT),
+            specials: SPECIALS.iter().map(|&special| (special, weight(special.sprite(), special.default_weight()))).collect()
+        }
+    }
+
+    pub fn weight(&self, kind: TargetKind, special: Option<Special>) -> u32 {
+        match special {
+            Some(special) => self.specials.iter().find(|&&(s, _)| s == special).map_or(0, |&(_, weight)| weight),
+            None if kind == TargetKind::Target => self.target,
+            None => self.decoy
+        }
+    }
+}
+
+impl Default for SpawnTable {
+    fn default() -> Self {
+        SpawnTable::from_query("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_weights() {
+        let table = SpawnTable::default();
+
+        assert_eq!(table.weight(TargetKind::Target, None), TARGET_WEIGHT);
+        assert_eq!(table.weight(TargetKind::Decoy, None), DECOY_WEIGHT);
+
+        for special in SPECIALS {
+            let kind = if special.is_target() { TargetKind::Target } else { TargetKind::Bomb };
+            assert_eq!(table.weight(kind, Some(special)), special.default_weight());
+        }
+    }
+
+    #[test]
+    fn query_overrides_weights() {
+        let table = SpawnTable::from_query("?target_weight=3&decoy_weight=0&bomb_weight=0&golden_weight=7&mode=slash");
+
+        assert_eq!(table.weight(TargetKind::Target, None), 3);
+        assert_eq!(table.weight(TargetKind::Decoy, None), 0);
+        assert_eq!(table.weight(TargetKind::Bomb, Some(Special::Bomb)), 0);
+        assert_eq!(table.weight(TargetKind::Target, Some(Special::Golden)), 7);
+        // the rest keep defaults
+        assert_eq!(table.weight(TargetKind::Target, Some(Special::Clock)), Special::Clock.default_weight());
+    }
+
+    #[test]
+    fn bad_weights_fall_back_to_defaults() {
+        let table = SpawnTable::from_query("?target_weight=-1&freeze_weight=lots");
+
+        assert_eq!(table.weight(TargetKind::Target, None), TARGET_WEIGHT);
+        assert_eq!(table.weight(TargetKind::Target, Some(Special::Freeze)), Special::Freeze.default_weight());
+    }
+
+    #[test]
+    fn special_weight_wins_over_kind() {
+        let table = SpawnTable::from_query("?target_weight=1&clock_weight=9");
+
+        // clock is a target, but has its own weight
+        assert_eq!(table.weight(TargetKind::Target, Some(Special::Clock)), 9);
+    }
+}
@@ -0,0 +1,330 @@
+#[allow(unused_imports)]
+use crate::log;
+
+use std::f32::consts::PI;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Square,
+    Sawtooth,
+    Sine,
+    Triangle,
+    // new random value every half period
+    Noise,
+}
+
+// Volume over time in seconds: rise, hold and fade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub sustain: f32,
+    // extra volume at the start of sustain, fades during it
+    pub punch: f32,
+    pub decay: f32
+}
+
+impl Envelope {
+    pub fn duration(&self) -> f32 {
+        self.attack + self.sustain + self.decay
+    }
+
+    pub fn volume_at(&self, t: f32) -> f32 {
+        if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.sustain {
+            1.0 + self.punch*(1.0 - (t - self.attack) / self.sustain)
+        } else if t < self.duration() {
+            1.0 - (t - self.attack - self.sustain) / self.decay
+        } else {
+            0.0
+        }
+    }
+}
+
+// Effect description in the style of sfxr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SynthParams {
+    pub waveform: Waveform,
+    // start frequency in Hz
+    pub frequency: f32,
+    // octaves per second, negative goes down
+    pub slide: f32,
+    // sound is cut when sliding below it
+    pub min_frequency: f32,
+    // part of period when square wave is high
+    pub duty: f32,
+    // part of frequency
+    pub vibrato_depth: f32,
+    // Hz
+    pub vibrato_speed: f32,
+    pub envelope: Envelope,
+    pub volume: f32,
+    // same seed gives the same noise
+    pub seed: u32
+}
+
+impl Default for SynthParams {
+    fn default() -> Self {
+        SynthParams {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            slide: 0.0,
+            min_frequency: 20.0,
+            duty: 0.5,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            envelope: Envelope { attack: 0.0, sustain: 0.1, punch: 0.0, decay: 0.1 },
+            volume: 0.5,
+            seed: 1
+        }
+    }
+}
+
+// Mono samples in [-1; 1]
+pub fn render(params: &SynthParams, sample_rate: u32) -> Vec<f32> {
+    let length = (params.envelope.duration()*sample_rate as f32) as usize;
+
+    let mut samples = Vec::with_capacity(length);
+
+    let mut rng = params.seed.max(1);
+    let mut noise = 0.0;
+    let mut phase = 0.0;
+
+    for i in 0..length {
+        let t = i as f32 / sample_rate as f32;
+
+        let vibrato = 1.0 + params.vibrato_depth*(2.0*PI*params.vibrato_speed*t).sin();
+        let frequency = params.frequency*(params.slide*t).exp2()*vibrato;
+
+        if frequency < params.min_frequency {
+            break;
+        }
+
+        let next = phase + frequency / sample_rate as f32;
+
+        // half period is over
+        if (2.0*next).floor() != (2.0*phase).floor() {
+            noise = next_noise(&mut rng);
+        }
+
+        phase = next.fract();
+
+        let value = match params.waveform {
+            Waveform::Square => if phase < params.duty { 1.0 } else { -1.0 },
+            Waveform::Sawtooth => 2.0*phase - 1.0,
+            Waveform::Sine => (2.0*PI*phase).sin(),
+            Waveform::Triangle => 4.0*(phase - 0.5).abs() - 1.0,
+            Waveform::Noise => noise
+        };
+
+        samples.push((value*params.envelope.volume_at(t)*params.volume).clamp(-1.0, 1.0));
+    }
+
+    samples
+}
+
+// xorshift mapped to [-1; 1]
+fn next_noise(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+
+    *state as f32 / u32::MAX as f32*2.0 - 1.0
+}
+
+// Presets of the game effects
+
+pub fn pop_up() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Sine,
+        frequency: 300.0,
+        slide: 8.0,
+        envelope: Envelope { attack: 0.005, sustain: 0.04, punch: 0.3, decay: 0.08 },
+        ..SynthParams::default()
+    }
+}
+
+pub fn hit() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Square,
+        frequency: 900.0,
+        slide: 2.5,
+        duty: 0.3,
+        envelope: Envelope { attack: 0.0, sustain: 0.03, punch: 0.6, decay: 0.17 },
+        volume: 0.35,
+        ..SynthParams::default()
+    }
+}
+
+pub fn miss() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Triangle,
+        frequency: 420.0,
+        slide: -4.5,
+        min_frequency: 100.0,
+        vibrato_depth: 0.05,
+        vibrato_speed: 12.0,
+        envelope: Envelope { attack: 0.01, sustain: 0.1, punch: 0.0, decay: 0.25 },
+        ..SynthParams::default()
+    }
+}
+
+pub fn bonus() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Square,
+        frequency: 600.0,
+        slide: 6.0,
+        duty: 0.25,
+        vibrato_depth: 0.1,
+        vibrato_speed: 20.0,
+        envelope: Envelope { attack: 0.0, sustain: 0.08, punch: 0.5, decay: 0.2 },
+        volume: 0.3,
+        ..SynthParams::default()
+    }
+}
+
+pub fn explosion() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Noise,
+        frequency: 300.0,
+        slide: -1.5,
+        envelope: Envelope { attack: 0.0, sustain: 0.1, punch: 0.8, decay: 0.5 },
+        volume: 0.6,
+        ..SynthParams::default()
+    }
+}
+
+pub fn decoy_hit() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Noise,
+        frequency: 1200.0,
+        slide: -2.0,
+        envelope: Envelope { attack: 0.0, sustain: 0.05, punch: 0.4, decay: 0.2 },
+        volume: 0.4,
+        ..SynthParams::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn length_matches_envelope() {
+        let params = SynthParams {
+            envelope: Envelope { attack: 0.1, sustain: 0.2, punch: 0.0, decay: 0.2 },
+            ..SynthParams::default()
+        };
+
+        assert_eq!(render(&params, SAMPLE_RATE).len(), (0.5*SAMPLE_RATE as f32) as usize);
+        assert_eq!(render(&params, 8000).len(), 4000);
+    }
+
+    #[test]
+    fn slide_below_min_frequency_cuts_sound() {
+        // two octaves down in half a second
+        let params = SynthParams {
+            frequency: 400.0,
+            slide: -4.0,
+            min_frequency: 100.0,
+            envelope: Envelope { attack: 0.0, sustain: 1.0, punch: 0.0, decay: 0.0 },
+            ..SynthParams::default()
+        };
+
+        assert!(render(&params, SAMPLE_RATE).len().abs_diff(SAMPLE_RATE as usize / 2) <= 1);
+    }
+
+    #[test]
+    fn samples_stay_in_range() {
+        let loud = SynthParams {
+            volume: 2.0,
+            envelope: Envelope { attack: 0.0, sustain: 0.1, punch: 1.0, decay: 0.1 },
+            ..SynthParams::default()
+        };
+
+        for params in [pop_up(), hit(), miss(), bonus(), explosion(), decoy_hit(), loud] {
+            assert!(render(&params, SAMPLE_RATE).iter().all(|v| (-1.0..=1.0).contains(v)), "{params:?}");
+        }
+    }
+
+    #[test]
+    fn silent_after_release() {
+        let params = hit();
+        let envelope = params.envelope;
+
+        assert_eq!(envelope.volume_at(envelope.duration()), 0.0);
+        assert_eq!(envelope.volume_at(envelope.duration() + 1.0), 0.0);
+
+        // the decay fades to zero by the end of the buffer
+        let samples = render(&params, SAMPLE_RATE);
+        let tail = &samples[samples.len() - 10..];
+
+        assert!(tail.iter().all(|v| v.abs() < 0.01), "{tail:?}");
+    }
+
+    #[test]
+    fn sawtooth_ramps_up_every_period() {
+        // 8 samples per period
+        let params = SynthParams {
+            waveform: Waveform::Sawtooth,
+            frequency: 1000.0,
+            volume: 1.0,
+            envelope: Envelope { attack: 0.0, sustain: 0.004, punch: 0.0, decay: 0.0 },
+            ..SynthParams::default()
+        };
+
+        let samples = render(&params, 8000);
+        assert_eq!(samples.len(), 32);
+
+        // phase is advanced before the first sample
+        let period: Vec<f32> = (1..=8).map(|i| 2.0*((i % 8) as f32 / 8.0) - 1.0).collect();
+
+        for (i, chunk) in samples.chunks(8).enumerate() {
+            assert!(chunk.iter().zip(&period).all(|(a, b)| (a - b).abs() < 1e-4), "period {i}: {chunk:?}");
+        }
+
+        // rises within the period, drops once at its end
+        let drops = samples.windows(2).filter(|pair| pair[1] < pair[0]).count();
+        assert_eq!(drops, 4);
+    }
+
+    #[test]
+    fn noise_depends_only_on_seed() {
+        let noise = |seed| render(&SynthParams { seed, ..explosion() }, SAMPLE_RATE);
+
+        assert_eq!(noise(7), noise(7));
+        assert_ne!(noise(7), noise(8));
+        // zero is a fixed point of xorshift, so it is replaced
+        assert_eq!(noise(0), noise(1));
+    }
+
+    #[test]
+    fn slide_changes_zero_crossing_rate() {
+        let sweep = |slide| SynthParams {
+            waveform: Waveform::Sine,
+            frequency: 200.0,
+            slide,
+            envelope: Envelope { attack: 0.0, sustain: 1.0, punch: 0.0, decay: 0.0 },
+            ..SynthParams::default()
+        };
+
+        let rising = render(&sweep(2.0), SAMPLE_RATE);
+        let (start, end) = rising.split_at(rising.len() / 2);
+        assert!(zero_crossings(end) > 2*zero_crossings(start));
+
+        let falling = render(&sweep(-1.0), SAMPLE_RATE);
+        let (start, end) = falling.split_at(falling.len() / 2);
+        assert!(zero_crossings(end) < zero_crossings(start));
+
+        // about two crossings per period without the slide
+        let flat = render(&sweep(0.0), SAMPLE_RATE);
+        assert!(zero_crossings(&flat).abs_diff(400) <= 2);
+    }
+}
@@ -0,0 +1,156 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::ecs::*;
+use crate::point::*;
+use crate::scene::*;
+use crate::collision::*;
+use crate::special::*;
+use crate::game_state::GameState;
+
+// Grid cell in webgl coords, about a figure size
+const GRID_CELL: f32 = 0.5;
+
+// Entities with collider and figure whose bounds touch the area
+// from the upper layers to the lower ones
+fn colliding_figures(world: &World, scene: &Scene, area: &Volume) -> Vec<(Entity, usize)> {
+    let Some(bounds) = area.bounds() else {
+        return Vec::new();
+    };
+
+    let mut grid = SpatialGrid::new(GRID_CELL);
+
+    for (entity, _) in world.colliders.iter() {
+        if let Some(figure) = world.figures.get(entity) {
+            if let Some(figure_bounds) = scene.figure_bounds(figure.0) {
+                grid.insert((entity, figure.0), &figure_bounds);
+            }
+        }
+    }
+
+    let mut candidates: Vec<(Entity, usize)> = grid.query(&bounds)
+        .into_iter()
+        .filter(|&(_, figure)| scene.figure_bounds(figure).is_some_and(|bounds| Volume::Aabb(bounds).overlaps(area)))
+        .collect();
+
+    candidates.sort_by_key(|&(_, figure)| std::cmp::Reverse(scene.figure_layer(figure)));
+
+    candidates
+}
+
+// Entity under the click, entities on the upper layers are checked first
+pub fn hit_system(world: &World, scene: &Scene, point: Point) -> Option<Entity> {
+    let area = Volume::Circle(Circle { center: point, radius: 0.0 });
+
+    colliding_figures(world, scene, &area).into_iter()
+        .find(|&(_, figure)| scene.is_dynamic_hit(figure, point))
+        .map(|(entity, _)| entity)
+}
+
+// All entities cut by the slash, unlike click it goes through every layer
+pub fn slash_system(world: &World, scene: &Scene, segment: &Segment) -> Vec<Entity> {
+    let area = Volume::Polygon(vec![segment.a, segment.b]);
+
+    colliding_figures(world, scene, &area).into_iter()
+        .filter(|&(_, figure)| scene.is_dynamic_slashed(figure, segment))
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+// Tweens move the props of their entities, runs every frame
+pub fn tween_system(world: &mut World, dt: f64) {
+    for (entity, tweens) in world.tweens.iter_mut() {
+        if let Some(props) = world.props.get_mut(entity) {
+            tweens.update(props, dt);
+        }
+    }
+}
+
+// Passes placement, props and new clips of entities to their scene figures, runs every frame
+pub fn render_system(world: &mut World, scene: &mut Scene) {
+    for (entity, &Figure(figure)) in world.figures.iter() {
+        let transform = world.transforms.get(entity).copied().unwrap_or_default();
+        let props = world.props.get(entity).copied().unwrap_or_default();
+
+        scene.set_figure_state(figure, transform, props);
+
+        if let Some(sprite) = world.sprites.get_mut(entity) {
+            if sprite.take_restart() {
+                scene.play(figure, sprite.clip());
+            }
+        }
+    }
+}
+
+// Extra points of the hit entity
+pub fn special_bonus(world: &World, entity: Entity) -> u64 {
+    match world.specials.get(entity) {
+        Some(Special::Golden) => GOLDEN_BONUS,
+        _ => 0
+    }
+}
+
+// What the hit entity does to the game, with a line for the score popup
+pub fn special_system(world: &World, entity: Entity, state: &mut GameState) -> Option<String> {
+    match world.specials.get(entity)? {
+        Special::Clock => {
+            state.add_time(CLOCK_TIME);
+            Some(format!("+{} s", CLOCK_TIME))
+        },
+        Special::Freeze => {
+            state.freeze(FREEZE_TIME);
+            Some(String::from("Freeze"))
+        },
+        Special::Golden | Special::Bomb => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tween::*;
+
+    #[test]
+    fn tweens_move_props_of_their_entity() {
+        let mut world = World::new();
+
+        let moving = world.spawn();
+        let still = world.spawn();
+
+        for entity in [moving, still] {
+            world.props.insert(entity, Props::default());
+            world.tweens.insert(entity, Tweener::new());
+        }
+
+        world.tweens.get_mut(moving).expect("Failed to get tweens").add(
+            Sequence::new().then(Tween::to(Property::OffsetY, 1.0, 1.0, Easing::Linear))
+        );
+
+        tween_system(&mut world, 0.5);
+        assert_eq!(world.props.get(moving).map(|props| props.offset_y), Some(0.5));
+
+        tween_system(&mut world, 1.0);
+        assert_eq!(world.props.get(moving).map(|props| props.offset_y), Some(1.0));
+        assert_eq!(world.props.get(still), Some(&Props::default()));
+    }
+
+    #[test]
+    fn specials_change_the_game_on_hit() {
+        let mut world = World::new();
+        let mut state = GameState::default();
+
+        let plain = world.spawn();
+        let golden = world.spawn();
+        let clock = world.spawn();
+        world.specials.insert(golden, Special::Golden);
+        world.specials.insert(clock, Special::Clock);
+
+        assert_eq!(special_bonus(&world, plain), 0);
+        assert_eq!(special_bonus(&world, golden), GOLDEN_BONUS);
+        assert_eq!(special_bonus(&world, clock), 0);
+
+        assert_eq!(special_system(&world, plain, &mut state), None);
+        assert_eq!(special_system(&world, golden, &mut state), None);
+        assert_eq!(special_system(&world, clock, &mut state), Some(format!("+{} s", CLOCK_TIME)));
+        assert_eq!(state.elapsed(), -CLOCK_TIME);
+    }
+}
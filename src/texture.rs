@@ -1,37 +1,46 @@
 #[allow(unused_imports)]
 use crate::log;
 
-use image::ImageReader;
-use std::io::Cursor;
-
 pub type Texture = Option<web_sys::WebGlTexture>;
 
-pub fn from_png_data(
-    gl: &web_sys::WebGl2RenderingContext,
-    raw_data: &[u8],
-    format: u32
-) -> Texture {
-    let reader = ImageReader::new(Cursor::new(raw_data))
-        .with_guessed_format()
-        .expect("Cursor io never fails");
+// Alpha channel of an image kept on cpu side for pixel precise hit tests
+#[derive(Debug, Clone)]
+pub struct AlphaMask {
+    width: u32,
+    height: u32,
+    alpha: Vec<u8>
+}
 
-    let image = reader.decode().unwrap();
+impl AlphaMask {
+    pub fn from_rgba(rgba_data: &[u8], width: u32, height: u32) -> AlphaMask {
+        AlphaMask {
+            width,
+            height,
+            alpha: rgba_data.chunks_exact(4).map(|pixel| pixel[3]).collect()
+        }
+    }
 
-    from_rgba_data(
-        gl,
-        image.as_bytes(),
-        image.width() as i32,
-        image.height() as i32,
-        format
-    )
+    // u and v in [0; 1], v = 0 is the top row
+    // points outside of the image are transparent
+    pub fn alpha_at(&self, u: f32, v: f32) -> u8 {
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return 0;
+        }
+
+        let x = ((u*self.width as f32) as u32).min(self.width - 1);
+        let y = ((v*self.height as f32) as u32).min(self.height - 1);
+
+        self.alpha[(y*self.width + x) as usize]
+    }
 }
 
-pub fn from_rgba_data(
+// Atlas pages hold several images side by side
+// so neither repeat nor mipmaps are allowed: both would bleed neighbours into each other
+pub fn from_atlas_data(
     gl: &web_sys::WebGl2RenderingContext,
     rgba_data: &[u8],
     width: i32,
-    height: i32,
-    format: u32
+    height: i32
 ) -> Texture {
     let texture = gl.create_texture();
 
@@ -42,29 +51,21 @@ pub fn from_rgba_data(
 
     gl.pixel_storei(web_sys::WebGl2RenderingContext::UNPACK_ALIGNMENT, 1);
 
-    let level = 0;
-    let internal_format = format;
-    let border = 0;
-    let src_format = format;
-    let src_type = web_sys::WebGl2RenderingContext::UNSIGNED_BYTE;
     gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
         web_sys::WebGl2RenderingContext::TEXTURE_2D,
-        level,
-        internal_format as i32,
+        0,
+        web_sys::WebGl2RenderingContext::RGBA as i32,
         width,
         height,
-        border,
-        src_format,
-        src_type,
+        0,
+        web_sys::WebGl2RenderingContext::RGBA,
+        web_sys::WebGl2RenderingContext::UNSIGNED_BYTE,
         Some(rgba_data),
     )
     .expect("Failed to write texture");
 
-    gl.generate_mipmap(web_sys::WebGl2RenderingContext::TEXTURE_2D);
-
-    gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_WRAP_T, web_sys::WebGl2RenderingContext::REPEAT as i32);
-    gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_WRAP_S, web_sys::WebGl2RenderingContext::REPEAT as i32);
-    gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_WRAP_R, web_sys::WebGl2RenderingContext::REPEAT as i32);
+    gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_WRAP_T, web_sys::WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_WRAP_S, web_sys::WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
     gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_MIN_FILTER, web_sys::WebGl2RenderingContext::LINEAR as i32);
     gl.tex_parameteri(web_sys::WebGl2RenderingContext::TEXTURE_2D, web_sys::WebGl2RenderingContext::TEXTURE_MAG_FILTER, web_sys::WebGl2RenderingContext::LINEAR as i32);
 
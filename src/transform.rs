@@ -0,0 +1,96 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::math::*;
+
+// Object placement: scale and rotation around the anchor, then translation
+// Objects with greater layer are drawn on top
+// There is no compose or inverse here: rotation after non uniform scale gives shear
+// which does not fit into the fields, so parents and picking multiply and invert `matrix()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+    // pivot in local coordinates
+    pub anchor: Vec2,
+    pub layer: i32
+}
+
+impl Transform {
+    pub fn id() -> Transform {
+        Transform {
+            translation: Vec2::zero(),
+            rotation: 0.0,
+            scale: Vec2::new(1.0, 1.0),
+            anchor: Vec2::zero(),
+            layer: 0
+        }
+    }
+
+    // T * R * S * T(-anchor)
+    pub fn matrix(&self) -> Mat3 {
+        Mat3::translation(self.translation)
+            * Mat3::rotation(self.rotation)
+            * Mat3::scale(self.scale)
+            * Mat3::translation(-self.anchor)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const EPS: f32 = 1e-5;
+
+    fn placement() -> Transform {
+        Transform {
+            translation: Vec2::new(0.5, -0.25),
+            rotation: PI / 3.0,
+            scale: Vec2::new(2.0, 0.5),
+            anchor: Vec2::new(0.0, -1.0),
+            layer: 2
+        }
+    }
+
+    #[test]
+    fn anchor_goes_to_translation() {
+        let transform = placement();
+
+        assert!(transform.matrix().transform_point(transform.anchor).distance(transform.translation) < EPS);
+        assert_eq!(Transform::default().matrix(), Mat3::id());
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        let matrix = placement().matrix();
+        let inverse = matrix.inverse().expect("Failed to invert placement");
+
+        for p in [Vec2::zero(), Vec2::new(1.0, 1.0), Vec2::new(-0.3, 0.7)] {
+            assert!(inverse.transform_point(matrix.transform_point(p)).distance(p) < EPS);
+            assert!(matrix.transform_point(inverse.transform_point(p)).distance(p) < EPS);
+        }
+    }
+
+    #[test]
+    fn composed_matrices_apply_child_first() {
+        let parent = placement();
+        let child = Transform { translation: Vec2::new(0.1, 0.2), rotation: -PI / 4.0, ..Transform::id() };
+
+        let world = parent.matrix() * child.matrix();
+        let p = Vec2::new(0.3, -0.4);
+
+        let expected = parent.matrix().transform_point(child.matrix().transform_point(p));
+        assert!(world.transform_point(p).distance(expected) < EPS);
+
+        // and back to the child space
+        let local = world.inverse().expect("Failed to invert world").transform_point(expected);
+        assert!(local.distance(p) < EPS);
+    }
+}
@@ -0,0 +1,480 @@
+#[allow(unused_imports)]
+use crate::log;
+use crate::point::*;
+use crate::transform::*;
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+// https://easings.net
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    BackIn,
+    BackOut,
+    ElasticOut,
+    BounceOut,
+}
+
+impl Easing {
+    // maps t from [0; 1] to the curve value, 0 -> 0 and 1 -> 1
+    pub fn apply(&self, t: f32) -> f32 {
+        const C1: f32 = 1.70158;
+        const C3: f32 = C1 + 1.0;
+
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t*t,
+            Easing::QuadOut => 1.0 - (1.0 - t)*(1.0 - t),
+            Easing::QuadInOut => if t < 0.5 { 2.0*t*t } else { 1.0 - (-2.0*t + 2.0).powi(2) / 2.0 },
+            Easing::CubicIn => t*t*t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => if t < 0.5 { 4.0*t*t*t } else { 1.0 - (-2.0*t + 2.0).powi(3) / 2.0 },
+            Easing::SineInOut => -((PI*t).cos() - 1.0) / 2.0,
+            Easing::BackIn => C3*t*t*t - C1*t*t,
+            Easing::BackOut => 1.0 + C3*(t - 1.0).powi(3) + C1*(t - 1.0).powi(2),
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2.0_f32.powf(-10.0*t)*((10.0*t - 0.75)*(2.0*PI / 3.0)).sin() + 1.0
+                }
+            },
+            Easing::BounceOut => {
+                const N1: f32 = 7.5625;
+                const D1: f32 = 2.75;
+
+                if t < 1.0 / D1 {
+                    N1*t*t
+                } else if t < 2.0 / D1 {
+                    let t = t - 1.5 / D1;
+                    N1*t*t + 0.75
+                } else if t < 2.5 / D1 {
+                    let t = t - 2.25 / D1;
+                    N1*t*t + 0.9375
+                } else {
+                    let t = t - 2.625 / D1;
+                    N1*t*t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+// Per object presentation values animated by tweens
+// applied on top of the object transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Props {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub rotation: f32,
+    pub alpha: f32
+}
+
+impl Default for Props {
+    fn default() -> Self {
+        Props {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            alpha: 1.0
+        }
+    }
+}
+
+impl Props {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: Point::new(self.offset_x, self.offset_y),
+            rotation: self.rotation,
+            scale: Point::new(self.scale_x, self.scale_y),
+            ..Transform::id()
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Property {
+    OffsetX,
+    OffsetY,
+    // both scale components
+    Scale,
+    ScaleX,
+    ScaleY,
+    Rotation,
+    Alpha,
+}
+
+impl Property {
+    fn get(&self, props: &Props) -> f32 {
+        match self {
+            Property::OffsetX => props.offset_x,
+            Property::OffsetY => props.offset_y,
+            Property::Scale | Property::ScaleX => props.scale_x,
+            Property::ScaleY => props.scale_y,
+            Property::Rotation => props.rotation,
+            Property::Alpha => props.alpha
+        }
+    }
+
+    fn set(&self, props: &mut Props, value: f32) {
+        match self {
+            Property::OffsetX => props.offset_x = value,
+            Property::OffsetY => props.offset_y = value,
+            Property::Scale => {
+                props.scale_x = value;
+                props.scale_y = value;
+            },
+            Property::ScaleX => props.scale_x = value,
+            Property::ScaleY => props.scale_y = value,
+            Property::Rotation => props.rotation = value,
+            Property::Alpha => props.alpha = value
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween {
+    property: Property,
+    // None means the value at the moment tween starts
+    from: Option<f32>,
+    to: f32,
+    duration: f64,
+    easing: Easing
+}
+
+impl Tween {
+    pub fn to(property: Property, to: f32, duration: f64, easing: Easing) -> Tween {
+        Tween {
+            property,
+            from: None,
+            to,
+            duration,
+            easing
+        }
+    }
+
+    pub fn from_to(property: Property, from: f32, to: f32, duration: f64, easing: Easing) -> Tween {
+        Tween {
+            property,
+            from: Some(from),
+            to,
+            duration,
+            easing
+        }
+    }
+}
+
+enum Step {
+    // tweens of the group run in parallel
+    Group(Vec<Tween>),
+    Wait(f64),
+    #[allow(unused)]
+    Call(Box<dyn FnMut()>),
+}
+
+impl std::fmt::Debug for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Group(tweens) => f.debug_tuple("Group").field(tweens).finish(),
+            Step::Wait(time) => f.debug_tuple("Wait").field(time).finish(),
+            Step::Call(_) => f.write_str("Call")
+        }
+    }
+}
+
+impl Step {
+    fn duration(&self) -> f64 {
+        match self {
+            Step::Group(tweens) => tweens.iter().map(|tween| tween.duration).fold(0.0, f64::max),
+            Step::Wait(time) => *time,
+            Step::Call(_) => 0.0
+        }
+    }
+}
+
+// Steps played one after another
+//
+// Sequence::new()
+//     .then(Tween::to(Property::Scale, 1.2, 0.1, Easing::QuadOut))
+//     .with(Tween::to(Property::Alpha, 1.0, 0.1, Easing::Linear))
+//     .then(Tween::to(Property::Scale, 1.0, 0.2, Easing::BounceOut))
+//     .call(|| log::write(&"done"))
+#[derive(Debug, Default)]
+pub struct Sequence {
+    steps: VecDeque<Step>,
+    // start values of the current group
+    start: Vec<f32>,
+    started: bool,
+    time: f64
+}
+
+impl Sequence {
+    pub fn new() -> Sequence {
+        Sequence {
+            steps: VecDeque::new(),
+            start: Vec::new(),
+            started: false,
+            time: 0.0
+        }
+    }
+
+    pub fn then(mut self, tween: Tween) -> Sequence {
+        self.steps.push_back(Step::Group(vec![tween]));
+        self
+    }
+
+    // runs together with the previous tween
+    pub fn with(mut self, tween: Tween) -> Sequence {
+        match self.steps.back_mut() {
+            Some(Step::Group(tweens)) => tweens.push(tween),
+            _ => self.steps.push_back(Step::Group(vec![tween]))
+        }
+
+        self
+    }
+
+    pub fn wait(mut self, time: f64) -> Sequence {
+        self.steps.push_back(Step::Wait(time));
+        self
+    }
+
+    #[allow(unused)]
+    pub fn call<F: FnMut() + 'static>(mut self, callback: F) -> Sequence {
+        self.steps.push_back(Step::Call(Box::new(callback)));
+        self
+    }
+
+    // returns true when the sequence is over
+    pub fn update(&mut self, props: &mut Props, dt: f64) -> bool {
+        let mut dt = dt;
+
+        while let Some(step) = self.steps.front_mut() {
+            if !self.started {
+                self.started = true;
+                self.time = 0.0;
+
+                if let Step::Group(tweens) = step {
+                    self.start = tweens.iter().map(|tween| tween.from.unwrap_or(tween.property.get(props))).collect();
+                }
+            }
+
+            self.time += dt;
+
+            let duration = step.duration();
+
+            match step {
+                Step::Group(tweens) => {
+                    for (tween, &from) in tweens.iter().zip(self.start.iter()) {
+                        let t = if tween.duration > 0.0 { (self.time / tween.duration) as f32 } else { 1.0 };
+
+                        tween.property.set(props, from + (tween.to - from)*tween.easing.apply(t));
+                    }
+                },
+                Step::Call(callback) => callback(),
+                Step::Wait(_) => {}
+            }
+
+            if self.time < duration {
+                return false;
+            }
+
+            // the rest of the frame goes to the next step
+            dt = self.time - duration;
+
+            self.started = false;
+            self.steps.pop_front();
+        }
+
+        true
+    }
+}
+
+// Runs independent sequences over one object
+#[derive(Debug, Default)]
+pub struct Tweener {
+    sequences: Vec<Sequence>
+}
+
+impl Tweener {
+    pub fn new() -> Tweener {
+        Tweener {
+            sequences: Vec::new()
+        }
+    }
+
+    pub fn add(&mut self, sequence: Sequence) {
+        self.sequences.push(sequence);
+    }
+
+    pub fn clear(&mut self) {
+        self.sequences.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.sequences.is_empty()
+    }
+
+    pub fn update(&mut self, props: &mut Props, dt: f64) {
+        self.sequences.retain_mut(|sequence| !sequence.update(props, dt));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    const EASINGS: [Easing; 12] = [
+        Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut, Easing::CubicIn, Easing::CubicOut,
+        Easing::CubicInOut, Easing::SineInOut, Easing::BackIn, Easing::BackOut, Easing::ElasticOut, Easing::BounceOut
+    ];
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn easings_keep_endpoints() {
+        for easing in EASINGS {
+            assert_near(easing.apply(0.0), 0.0);
+            assert_near(easing.apply(1.0), 1.0);
+
+            // t out of range is clamped
+            assert_near(easing.apply(-1.0), 0.0);
+            assert_near(easing.apply(2.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn easings_have_their_shape() {
+        assert_near(Easing::Linear.apply(0.25), 0.25);
+        assert!(Easing::QuadIn.apply(0.5) < 0.5);
+        assert!(Easing::QuadOut.apply(0.5) > 0.5);
+
+        for easing in [Easing::QuadInOut, Easing::CubicInOut, Easing::SineInOut] {
+            assert_near(easing.apply(0.5), 0.5);
+        }
+
+        // back curves overshoot, bounce stays in range
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+        assert!(Easing::ElasticOut.apply(0.1) > 1.0);
+        assert!((0..=100).map(|i| Easing::BounceOut.apply(i as f32 / 100.0)).all(|v| (0.0..=1.0).contains(&v)));
+    }
+
+    #[test]
+    fn steps_run_in_order() {
+        let mut props = Props::default();
+
+        let mut sequence = Sequence::new()
+            .then(Tween::to(Property::OffsetX, 1.0, 1.0, Easing::Linear))
+            .then(Tween::to(Property::OffsetY, 1.0, 1.0, Easing::Linear));
+
+        assert!(!sequence.update(&mut props, 0.5));
+        assert_near(props.offset_x, 0.5);
+        assert_near(props.offset_y, 0.0);
+
+        // the rest of the frame goes to the second tween
+        assert!(!sequence.update(&mut props, 1.0));
+        assert_near(props.offset_x, 1.0);
+        assert_near(props.offset_y, 0.5);
+
+        assert!(sequence.update(&mut props, 0.5));
+        assert_near(props.offset_y, 1.0);
+    }
+
+    #[test]
+    fn grouped_tweens_run_together() {
+        let mut props = Props::default();
+
+        let mut sequence = Sequence::new()
+            .then(Tween::from_to(Property::Scale, 0.0, 2.0, 1.0, Easing::Linear))
+            .with(Tween::to(Property::Rotation, 1.0, 2.0, Easing::Linear))
+            .then(Tween::to(Property::Alpha, 0.0, 1.0, Easing::Linear));
+
+        sequence.update(&mut props, 0.5);
+        assert_eq!((props.scale_x, props.scale_y, props.rotation, props.alpha), (1.0, 1.0, 0.25, 1.0));
+
+        // the group lasts as long as its longest tween
+        sequence.update(&mut props, 1.0);
+        assert_eq!((props.scale_x, props.rotation, props.alpha), (2.0, 0.75, 1.0));
+
+        assert!(!sequence.update(&mut props, 1.0));
+        assert_near(props.alpha, 0.5);
+    }
+
+    #[test]
+    fn tween_starts_from_the_current_value() {
+        let mut props = Props { scale_x: 2.0, scale_y: 3.0, ..Props::default() };
+
+        let mut sequence = Sequence::new()
+            .wait(1.0)
+            .then(Tween::to(Property::ScaleY, 1.0, 1.0, Easing::Linear));
+
+        // changed while the sequence waits
+        sequence.update(&mut props, 0.5);
+        props.scale_y = 5.0;
+
+        sequence.update(&mut props, 0.5);
+        sequence.update(&mut props, 0.5);
+
+        assert_eq!(props.scale_x, 2.0);
+        assert_near(props.scale_y, 3.0);
+    }
+
+    #[test]
+    fn callbacks_run_once_in_their_turn() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let (first, second) = (calls.clone(), calls.clone());
+
+        let mut props = Props::default();
+
+        let mut sequence = Sequence::new()
+            .call(move || first.borrow_mut().push("start"))
+            .then(Tween::to(Property::OffsetX, 1.0, 1.0, Easing::Linear))
+            .call(move || second.borrow_mut().push("end"));
+
+        assert!(!sequence.update(&mut props, 0.5));
+        assert_eq!(*calls.borrow(), vec!["start"]);
+
+        assert!(sequence.update(&mut props, 0.5));
+        assert_eq!(*calls.borrow(), vec!["start", "end"]);
+
+        assert!(sequence.update(&mut props, 0.5));
+        assert_eq!(calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn tweener_drops_finished_sequences() {
+        let mut props = Props::default();
+        let mut tweener = Tweener::new();
+
+        tweener.add(Sequence::new().then(Tween::to(Property::OffsetX, 1.0, 0.5, Easing::Linear)));
+        tweener.add(Sequence::new().then(Tween::to(Property::OffsetY, 1.0, 1.0, Easing::Linear)));
+
+        tweener.update(&mut props, 0.5);
+        assert!(tweener.is_active());
+
+        tweener.update(&mut props, 0.5);
+        assert!(!tweener.is_active());
+        assert_eq!((props.offset_x, props.offset_y), (1.0, 1.0));
+    }
+}
@@ -2,12 +2,39 @@ use wasm_bindgen::prelude::*;
 
 #[allow(unused_imports)]
 use crate::log;
+use crate::point::*;
+use crate::leaderboard::*;
+use crate::events::*;
+
+use std::collections::VecDeque;
+
+// Panel box (leaderboard, game over) in parts of the canvas size
+const PANEL_LEFT: f64 = 0.15;
+const PANEL_TOP: f64 = 0.12;
+const PANEL_LINE: f64 = 0.05;
+
+// Seconds a toast is shown, the last part of it fades out
+const TOAST_TIME: f64 = 3.0;
+const TOAST_FADE: f64 = 0.5;
 
 #[derive(Debug)]
 pub struct Ui {
     ctx: web_sys::CanvasRenderingContext2d,
     score_area: Label,
-    time_area: Label,
+    best_area: Label,
+    // mode specific, e.g. lives left
+    status_area: Label,
+    toast_area: Label,
+    // waiting ones, the front one is shown
+    toasts: VecDeque<String>,
+    // seconds the front toast is shown
+    toast_time: f64,
+    // labels floating over the scene, redrawn every frame
+    popups: Vec<Label>,
+    // panel box is on the canvas
+    panel_shown: bool,
+    width: f64,
+    height: f64
 }
 
 impl Ui {
@@ -32,10 +59,27 @@ impl Ui {
             h: 0.01*h as f64
         };
 
-        let time_label = Label {
-            x: (-0.0 + 1.0)*(w as f64 / 2.0), // map [-1; 1] to [0; w]
-            y: (0.9 - 1.0)*(h as f64 / -2.0),
-            w: 0.5*w as f64,
+        // right to the score label
+        let best_label = Label {
+            x: (0.4 + 1.0)*(w as f64 / 2.0),
+            y: (0.95 - 1.0)*(h as f64 / -2.0),
+            w: 0.2*w as f64,
+            h: 0.01*h as f64
+        };
+
+        // left to the score label
+        let status_label = Label {
+            x: (-0.6 + 1.0)*(w as f64 / 2.0),
+            y: (0.95 - 1.0)*(h as f64 / -2.0),
+            w: 0.35*w as f64,
+            h: 0.01*h as f64
+        };
+
+        // under the score line
+        let toast_label = Label {
+            x: (-0.0 + 1.0)*(w as f64 / 2.0),
+            y: (0.85 - 1.0)*(h as f64 / -2.0),
+            w: 0.8*w as f64,
             h: 0.01*h as f64
         };
 
@@ -44,7 +88,15 @@ impl Ui {
         Ui {
             ctx,
             score_area: score_label,
-            time_area: time_label
+            best_area: best_label,
+            status_area: status_label,
+            toast_area: toast_label,
+            toasts: VecDeque::new(),
+            toast_time: 0.0,
+            popups: Vec::new(),
+            panel_shown: false,
+            width: w as f64,
+            height: h as f64
         }
     }
 
@@ -53,13 +105,113 @@ impl Ui {
         self.score_area.draw(&self.ctx, &format!("Score = {}", score));
     }
 
-    pub fn set_time(&mut self, t: f64) {
-        self.clear_timer();
-        self.time_area.draw(&self.ctx, &format!("{:.2}", t));
+    pub fn set_best(&mut self, score: u64) {
+        self.best_area.clear(&self.ctx);
+        self.best_area.draw(&self.ctx, &format!("Best: {}", score));
+    }
+
+    pub fn set_status(&mut self, text: &str) {
+        self.status_area.clear(&self.ctx);
+        self.status_area.draw(&self.ctx, text);
+    }
+
+    // Shown after the ones before it are gone
+    pub fn toast(&mut self, text: &str) {
+        self.toasts.push_back(String::from(text));
+    }
+
+    pub fn update_toasts(&mut self, dt: f64) {
+        self.toast_area.clear(&self.ctx);
+
+        let Some(text) = self.toasts.front() else {
+            return;
+        };
+
+        self.toast_time += dt;
+
+        if self.toast_time >= TOAST_TIME {
+            self.toasts.pop_front();
+            self.toast_time = 0.0;
+            return;
+        }
+
+        self.ctx.set_global_alpha(((TOAST_TIME - self.toast_time) / TOAST_FADE).min(1.0));
+        self.toast_area.draw(&self.ctx, text);
+        self.ctx.set_global_alpha(1.0);
+    }
+
+    // position in webgl coords, text and alpha
+    pub fn set_popups(&mut self, popups: &[(Point, String, f32)]) {
+        for popup in self.popups.iter_mut() {
+            popup.clear(&self.ctx);
+        }
+
+        self.popups.clear();
+
+        for (position, text, alpha) in popups {
+            let mut label = Label {
+                x: (position.x() as f64 + 1.0)*(self.width / 2.0), // map [-1; 1] to [0; w]
+                y: (position.y() as f64 - 1.0)*(self.height / -2.0),
+                w: 0.2*self.width,
+                h: 0.02*self.height
+            };
+
+            self.ctx.set_global_alpha(*alpha as f64);
+            label.draw(&self.ctx, text);
+            self.ctx.set_global_alpha(1.0);
+
+            self.popups.push(label);
+        }
+    }
+
+    pub fn draw_leaderboard(&mut self, view: &LeaderboardView) {
+        self.draw_panel(&view.lines());
+    }
+
+    // Redrawn every frame while shown, popups must not cut holes in it
+    pub fn draw_panel(&mut self, lines: &[String]) {
+        self.clear_panel();
+
+        let (x, y) = (PANEL_LEFT*self.width, PANEL_TOP*self.height);
+        let (w, h) = ((1.0 - 2.0*PANEL_LEFT)*self.width, (1.0 - 2.0*PANEL_TOP)*self.height);
+
+        self.ctx.save();
+
+        self.ctx.set_fill_style_str("rgba(0, 0, 0, 0.75)");
+        self.ctx.fill_rect(x, y, w, h);
+
+        self.ctx.set_fill_style_str("white");
+        self.ctx.set_font(&format!("{}px sans-serif", (0.6*PANEL_LINE*self.height) as u32));
+
+        for (i, text) in lines.iter().enumerate() {
+            self.ctx.fill_text(text, self.width / 2.0, y + (i + 1) as f64*PANEL_LINE*self.height).unwrap();
+        }
+
+        self.ctx.restore();
+
+        self.panel_shown = true;
+    }
+
+    pub fn clear_panel(&mut self) {
+        if !self.panel_shown {
+            return;
+        }
+
+        self.ctx.clear_rect(PANEL_LEFT*self.width, PANEL_TOP*self.height, (1.0 - 2.0*PANEL_LEFT)*self.width, (1.0 - 2.0*PANEL_TOP)*self.height);
+
+        self.panel_shown = false;
     }
+}
 
-    pub fn clear_timer(&mut self) {
-        self.time_area.clear(&self.ctx);
+impl Subscriber for Ui {
+    fn on_event(&mut self, event: &GameEvent, _follow_ups: &mut Vec<GameEvent>) {
+        match event {
+            GameEvent::GameStarted { .. } => self.set_score(0),
+            GameEvent::GameResumed { score, .. } | GameEvent::TargetHit { score, .. } => self.set_score(*score),
+            GameEvent::NewBest { score } => self.set_best(*score),
+            GameEvent::AchievementUnlocked { achievement } => self.toast(&format!("Achievement: {}", achievement.title)),
+            _ => {}
+        }
     }
 }
 
@@ -0,0 +1,28 @@
+#[allow(unused_imports)]
+use crate::log;
+
+// Value of `name` in page url query, e.g. `?mode=slash&round_time=2`
+pub fn query_value<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_values_by_exact_name() {
+        let query = "?mode=slash&round_time=2&volume=&game_mode=zen";
+
+        assert_eq!(query_value(query, "mode"), Some("slash"));
+        assert_eq!(query_value(query, "round_time"), Some("2"));
+        assert_eq!(query_value(query, "volume"), Some(""));
+        // names are not matched by prefix or suffix
+        assert_eq!(query_value(query, "round"), None);
+        assert_eq!(query_value(query, "game"), None);
+        assert_eq!(query_value("", "mode"), None);
+        assert_eq!(query_value("mode=tap", "mode"), Some("tap"));
+    }
+}